@@ -3,34 +3,28 @@ use crate::{
     grammar::{object::Object, token::Token},
 };
 
-use rand::distributions::Alphanumeric;
-use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
 use std::{
     cell::RefCell,
     collections::HashMap,
     rc::Rc,
-    time::{SystemTime, UNIX_EPOCH},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
-pub fn generate_id() -> String {
-    // Obtain the current system time as a seed
-    let seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_nanos();
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+static NEXT_OBJECT_ID: AtomicUsize = AtomicUsize::new(0);
 
-    // Initialize a random number generator with the current system time as the seed
-    let rng = StdRng::seed_from_u64(seed as u64);
-
-    // Generate a random string of 5 alphanumeric characters
-    let id: String = rng
-        .sample_iter(&Alphanumeric)
-        .take(5)
-        .map(char::from)
-        .collect();
+/// # generate_id
+/// Returns the id of a new token. Ids are handed out in order, so they can index a vector.
+///
+/// Only the scanner and the parser take ids, so the ids in use grow with the amount of source, not with how long it has run. Tokens made up at runtime use `SYNTHETIC_ID` instead.
+pub fn generate_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
 
-    id
+/// # generate_object_id
+/// Returns an id that tells a class or an instance apart from every other. It is counted separately from token ids, so creating objects does not grow the resolver's side table.
+pub fn generate_object_id() -> usize {
+    NEXT_OBJECT_ID.fetch_add(1, Ordering::Relaxed)
 }
 
 #[warn(dead_code)]
@@ -43,31 +37,52 @@ pub fn generate_id() -> String {
 /// - a block is entered
 /// - a subclass is instantiated
 /// - a class with a superclass is created
+///
+/// Globals live in the root environment and are looked up by name, because a function can refer to a global that is declared after it. Every other variable was given a slot by the resolver and lives in `slots`.
 pub struct Environment {
     pub values: HashMap<String, Object>,
+    /// Local variables in the order they were declared. The resolver numbers them the same way.
+    pub slots: Vec<Object>,
     pub enclosing: Option<Rc<RefCell<Environment>>>,
-    pub _name: String,
 }
 
 impl Environment {
     pub fn new() -> Self {
         Self {
-            _name: generate_id(),
             enclosing: None,
             values: HashMap::new(),
+            slots: Vec::new(),
         }
     }
 
+    /// Prints the environment's address, which tells environments apart when debugging.
     pub fn _print_name(&self) {
-        println!("{}", self._name);
+        match self.enclosing {
+            Some(_) => println!("{:p}-child", self),
+            None => println!("{:p}", self),
+        }
+    }
+
+    /// # root
+    /// The environment at the end of the chain, which holds the globals of the module the chain belongs to.
+    pub fn root(environment: &Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        let mut environment = environment.clone();
+
+        loop {
+            let enclosing = environment.borrow().enclosing.clone();
+            match enclosing {
+                Some(enclosing) => environment = enclosing,
+                None => return environment,
+            }
+        }
     }
 
     // Secondary constructor: With an enclosing environment
     pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
         Self {
-            _name: format!("{}-child", generate_id()),
             enclosing: Some(enclosing),
             values: HashMap::new(),
+            slots: Vec::new(),
         }
     }
 
@@ -97,20 +112,29 @@ impl Environment {
         }
     }
 
+    /// # define
+    /// Globals are stored by name. A local takes the next slot, so locals have to be defined in the order the resolver declared them.
     pub fn define(&mut self, name: String, value: Object) {
-        self.values.insert(name, value);
+        match self.enclosing {
+            Some(_) => self.slots.push(value),
+            None => {
+                self.values.insert(name, value);
+            }
+        }
     }
 
+    /// # ancestor
+    /// The environment `distance` steps up the chain, for a distance of one or more. Only the `Rc` handles are cloned, so the frame that comes back is the real one, and a write through it is seen by every closure sharing it.
+    ///
+    /// Distance 0 is the environment itself, which callers use directly.
     fn ancestor(&self, distance: usize) -> Option<Rc<RefCell<Environment>>> {
-        let mut environment = Rc::new(RefCell::new(self.clone()));
-        for _ in 0..distance {
-            environment = match environment.clone().borrow().enclosing.as_ref() {
-                Some(e) => e.clone(),
-                None => return None,
-            }
+        let mut environment = self.enclosing.clone()?;
+        for _ in 1..distance {
+            let enclosing = environment.borrow().enclosing.clone()?;
+            environment = enclosing;
         }
 
-        return Some(environment);
+        Some(environment)
     }
 
     pub fn _values_of(&self) -> &HashMap<String, Object> {
@@ -133,44 +157,69 @@ impl Environment {
         }
     }
 
-    pub fn get_at(&self, distance: usize, token: &Token) -> Result<Object, RuntimeError> {
-        let ancestor = self.ancestor(distance);
-        let name = &token.lexeme;
+    pub fn get_at(
+        &self,
+        distance: usize,
+        slot: usize,
+        token: &Token,
+    ) -> Result<Object, RuntimeError> {
+        let undefined = || {
+            RuntimeError::new(
+                format!(
+                    "Undefined variable '{}' -- in Environment::get_at().",
+                    token.lexeme
+                ),
+                token,
+            )
+        };
 
-        if let Some(a) = ancestor {
-            a.borrow().values.get(name).cloned().ok_or_else(|| {
-                RuntimeError::new(
-                    format!("Undefined variable '{}' -- in Environment::get_at().", name),
-                    token,
-                )
-            })
-        } else {
-            return Ok(Object::Nil);
+        if distance == 0 {
+            return self.slots.get(slot).cloned().ok_or_else(undefined);
+        }
+
+        match self.ancestor(distance) {
+            Some(ancestor) => ancestor
+                .borrow()
+                .slots
+                .get(slot)
+                .cloned()
+                .ok_or_else(undefined),
+            None => Err(undefined()),
         }
     }
 
     pub fn assign_at(
         &mut self,
         distance: usize,
+        slot: usize,
         token: &Token,
         value: Object,
     ) -> Result<Object, RuntimeError> {
-        match self.ancestor(distance) {
-            Some(a) => {
-                a.clone()
-                    .borrow_mut()
-                    .values
-                    .insert(token.lexeme.clone(), value.clone());
-
-                Ok(value)
-            }
-            None => Err(RuntimeError::new(
+        let undefined = || {
+            RuntimeError::new(
                 format!(
                     "Undefined variable '{}' -- in Environment::assign_at().",
                     token.lexeme
                 ),
                 token,
-            )),
+            )
+        };
+
+        if distance == 0 {
+            let current = self.slots.get_mut(slot).ok_or_else(undefined)?;
+            *current = value.clone();
+            return Ok(value);
+        }
+
+        match self.ancestor(distance) {
+            Some(ancestor) => {
+                let mut ancestor = ancestor.borrow_mut();
+                let current = ancestor.slots.get_mut(slot).ok_or_else(undefined)?;
+                *current = value.clone();
+
+                Ok(value)
+            }
+            None => Err(undefined()),
         }
     }
 }
@@ -256,4 +305,95 @@ mod tests {
             _ => panic!("Value not found or not a number"),
         };
     }
+
+    #[test]
+    fn test_locals_are_stored_in_slots() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals
+            .borrow_mut()
+            .define("global".to_string(), Object::Num(1.0));
+
+        let mut local = Environment::with_enclosing(globals.clone());
+        local.define("a".to_string(), Object::Num(2.0));
+        local.define("a".to_string(), Object::Num(3.0));
+
+        let token = Token::new(
+            TokenType::Identifier,
+            "a".to_string(),
+            None,
+            1,
+            generate_id(),
+        );
+
+        assert!(
+            local.values.is_empty(),
+            "Locals should not be stored by name"
+        );
+        match local.get_at(0, 1, &token) {
+            Ok(Object::Num(n)) => assert_eq!(n, 3.0),
+            _ => panic!("Expected the second slot"),
+        };
+
+        local.assign_at(0, 0, &token, Object::Num(4.0)).unwrap();
+        match local.get_at(0, 0, &token) {
+            Ok(Object::Num(n)) => assert_eq!(n, 4.0),
+            _ => panic!("Expected the assigned value"),
+        };
+
+        assert!(globals.borrow().values.contains_key("global"));
+        assert!(local.get_at(0, 2, &token).is_err());
+    }
+
+    #[test]
+    fn test_assign_through_ancestor_is_seen_by_every_closure() {
+        // a function's frame holding `count`, captured by two closures that each run in their own frame
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let captured = Rc::new(RefCell::new(Environment::with_enclosing(globals)));
+        captured
+            .borrow_mut()
+            .define("count".to_string(), Object::Num(0.0));
+
+        let increment = Environment::with_enclosing(captured.clone());
+        let mut increment_body = Environment::with_enclosing(Rc::new(RefCell::new(increment)));
+        let read = Environment::with_enclosing(captured.clone());
+
+        let token = Token::new(
+            TokenType::Identifier,
+            "count".to_string(),
+            None,
+            1,
+            generate_id(),
+        );
+
+        increment_body
+            .assign_at(2, 0, &token, Object::Num(1.0))
+            .unwrap();
+        increment_body
+            .assign_at(2, 0, &token, Object::Num(2.0))
+            .unwrap();
+
+        match read.get_at(1, 0, &token) {
+            Ok(Object::Num(n)) => assert_eq!(n, 2.0),
+            _ => panic!("The other closure should see the write"),
+        };
+        match captured.borrow().get_at(0, 0, &token) {
+            Ok(Object::Num(n)) => assert_eq!(n, 2.0),
+            _ => panic!("The captured frame itself should hold the write"),
+        };
+    }
+
+    #[test]
+    fn test_get_at_past_the_root_is_an_error() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let local = Environment::with_enclosing(globals);
+        let token = Token::new(
+            TokenType::Identifier,
+            "a".to_string(),
+            None,
+            1,
+            generate_id(),
+        );
+
+        assert!(local.get_at(2, 0, &token).is_err());
+    }
 }
@@ -0,0 +1,53 @@
+use std::fmt;
+
+use super::runtime_error::RuntimeError;
+use crate::grammar::token::Token;
+
+#[derive(Debug, Clone, PartialEq)]
+/// # ConversionError
+///
+/// Raised by `FromLox` when a Lox value has the wrong type for the Rust value asked for, or is out of its range.
+///
+/// A conversion has no place in the source, so the error carries only its message. `at` makes it a runtime error at a token. A native function can return it with `?`, and it becomes a runtime error at the native's call.
+pub struct ConversionError {
+    message: String,
+}
+
+impl ConversionError {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// # context
+    /// Puts where in a larger value the conversion failed in front of the message, like the index of a list element.
+    pub fn context(self, context: &str) -> Self {
+        Self {
+            message: format!("{}: {}", context, self.message),
+        }
+    }
+
+    /// # at
+    /// The runtime error for this conversion, reported at the token.
+    pub fn at(self, token: &Token) -> RuntimeError {
+        RuntimeError::new(self.message, token)
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Native functions fail with a plain message, so `?` on a conversion works inside them.
+impl From<ConversionError> for String {
+    fn from(error: ConversionError) -> Self {
+        error.message
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+use super::error::{snippet, traceback};
+use super::runtime_error::CallFrame;
+use super::LoxError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// # DiagnosticKind
+/// The stage of a run that found the problem.
+pub enum DiagnosticKind {
+    /// The scanner could not read a token.
+    Scan,
+    /// The tokens do not form a valid program.
+    Parse,
+    /// The program failed while it was resolved or run.
+    Runtime,
+    /// The run used up its fuel or its time.
+    Limit,
+}
+
+#[derive(Debug, Clone)]
+/// # Diagnostic
+///
+/// An error found while running Lox source, in a form that can be inspected as well as printed. Display prints it the way the interpreter reports errors.
+///
+/// - kind
+///   - Which stage of the run found the error
+/// - message
+///   - What went wrong
+/// - line
+///   - The line of the token the error was found at. Limit errors have no line and use 0
+/// - column
+///   - The column of that token, counted in characters from 1, if the token came from the source
+/// - lexeme
+///   - The text of the token a parse error was found at
+/// - backtrace
+///   - The calls that were running when a runtime error was raised, innermost first
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub lexeme: Option<String>,
+    pub backtrace: Vec<CallFrame>,
+    snippet: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn scan(line: usize, message: &str) -> Self {
+        Self {
+            kind: DiagnosticKind::Scan,
+            message: message.to_string(),
+            line,
+            column: None,
+            lexeme: None,
+            backtrace: Vec::new(),
+            snippet: None,
+        }
+    }
+
+    /// # snippet
+    /// The source line the error was found on, with the token underlined.
+    pub fn snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+}
+
+impl From<LoxError> for Diagnostic {
+    fn from(error: LoxError) -> Self {
+        match error {
+            LoxError::ParseError(error) => {
+                let (message, token) = error.get_error();
+                Self {
+                    kind: DiagnosticKind::Parse,
+                    message: message.to_string(),
+                    line: token.line,
+                    column: token.span.source.as_ref().map(|_| token.span.column),
+                    lexeme: Some(token.lexeme.clone()),
+                    backtrace: Vec::new(),
+                    snippet: snippet(&token.span),
+                }
+            }
+            LoxError::RuntimeError(error) => {
+                let (message, token) = error.get_error();
+                Self {
+                    kind: DiagnosticKind::Runtime,
+                    message: message.to_string(),
+                    line: token.line,
+                    column: token.span.source.as_ref().map(|_| token.span.column),
+                    lexeme: None,
+                    backtrace: error.backtrace().to_vec(),
+                    snippet: snippet(&token.span),
+                }
+            }
+            LoxError::LimitError(error) => Self {
+                kind: DiagnosticKind::Limit,
+                message: error.to_string(),
+                line: 0,
+                column: None,
+                lexeme: None,
+                backtrace: Vec::new(),
+                snippet: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DiagnosticKind::Scan => write!(f, "[line {}] Error: {}", self.line, self.message)?,
+            DiagnosticKind::Parse => write!(
+                f,
+                "Parse Error - [line {}] Error at: {:?} - {}",
+                self.line,
+                self.lexeme.as_deref().unwrap_or_default(),
+                self.message
+            )?,
+            DiagnosticKind::Runtime => {
+                write!(f, "Runtime Error - [line {}]: {}", self.line, self.message)?
+            }
+            DiagnosticKind::Limit => write!(f, "Limit Error - {}", self.message)?,
+        }
+
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{}", snippet)?;
+        }
+        if let Some(traceback) = traceback(self.line, &self.backtrace) {
+            write!(f, "\n{}", traceback)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
@@ -1,48 +1,239 @@
-use super::parse_error::ParseError;
-use super::runtime_error::RuntimeError;
+use std::io::{self, Write};
 
+use super::diagnostic::Diagnostic;
+use super::runtime_error::CallFrame;
+use crate::grammar::token::Span;
+
+/// # ErrorReporter
+/// Collects the errors the scanner finds, and writes the errors of a run to its sink. Scanning goes on after an error, so every bad token in the source is reported.
 pub struct ErrorReporter {
-    had_error: bool,
+    diagnostics: Vec<Diagnostic>,
+    /// Where `report` writes. Standard error unless the host redirects it.
+    output: Box<dyn Write>,
 }
 
 impl ErrorReporter {
     pub fn new() -> Self {
-        Self { had_error: false }
+        Self::with_output(Box::new(io::stderr()))
+    }
+
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        Self {
+            diagnostics: Vec::new(),
+            output,
+        }
     }
 
-    pub fn set_error(&mut self, had_error: bool) {
-        self.had_error = had_error;
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    /// # report
+    /// Writes the error to the sink.
+    pub fn report(&mut self, diagnostic: &Diagnostic) {
+        // there is nowhere left to report a failure to write an error
+        let _ = writeln!(self.output, "{}", diagnostic);
     }
 
     pub fn had_error(&self) -> bool {
-        self.had_error
+        !self.diagnostics.is_empty()
     }
 
     pub fn report_error_message(&mut self, line: usize, message: &str) {
-        self.report(line, "", message);
+        self.diagnostics.push(Diagnostic::scan(line, message));
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+/// How many times the same entry is shown in a row before the rest of the run is counted instead.
+const TRACEBACK_REPEATS_SHOWN: usize = 3;
+
+/// # traceback
+/// Lists the calls that led to an error, innermost first. Each frame is shown with the line it had reached, which is where the error happened for the innermost frame and the call site of the frame above it for the others.
+///
+/// Deep recursion repeats one entry many times, so only the first few of a run are listed.
+pub fn traceback(line: usize, backtrace: &[CallFrame]) -> Option<String> {
+    if backtrace.is_empty() {
+        return None;
+    }
+
+    let mut entries = Vec::new();
+    let mut line = line;
+    for frame in backtrace {
+        entries.push(format!("[line {}] in {}", line, frame));
+        line = frame.line;
+    }
+    entries.push(format!("[line {}] in script", line));
+
+    let mut traceback = String::from("Traceback (most recent call first):");
+    let mut index = 0;
+    while index < entries.len() {
+        let run = entries[index..]
+            .iter()
+            .take_while(|entry| **entry == entries[index])
+            .count();
+
+        for entry in &entries[index..index + run.min(TRACEBACK_REPEATS_SHOWN)] {
+            traceback.push_str(&format!("\n  {}", entry));
+        }
+        if run > TRACEBACK_REPEATS_SHOWN {
+            traceback.push_str(&format!(
+                "\n  [Previous line repeated {} more times]",
+                run - TRACEBACK_REPEATS_SHOWN
+            ));
+        }
+
+        index += run;
+    }
+
+    Some(traceback)
+}
+
+/// # snippet
+/// Shows the source line a span is on, with the span underlined, in the style of rustc:
+///
+/// ```text
+///   --> line 3, column 11
+///    |
+///  3 | print "a" + nil;
+///    |           ^
+/// ```
+///
+/// A span that runs over several lines is underlined to the end of its first line. Spans without a source have no snippet.
+pub fn snippet(span: &Span) -> Option<String> {
+    let source = span.source.as_ref()?;
+
+    let line_start = source[..span.offset]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let line_end = source[span.offset..]
+        .find('\n')
+        .map_or(source.len(), |index| span.offset + index);
+    let line_number = source[..line_start].matches('\n').count() + 1;
+
+    // tabs before the span are kept so the carets line up however wide the terminal draws them
+    let indent: String = source[line_start..span.offset]
+        .chars()
+        .map(|character| if character == '\t' { '\t' } else { ' ' })
+        .collect();
+    let underline_end = (span.offset + span.length).min(line_end);
+    let carets = "^".repeat(source[span.offset..underline_end].chars().count().max(1));
+
+    let gutter = " ".repeat(line_number.to_string().len());
+    Some(format!(
+        "{gutter}--> line {line_number}, column {column}\n{gutter} |\n{line_number} | {line}\n{gutter} | {indent}{carets}",
+        column = span.column,
+        line = source[line_start..line_end].trim_end_matches('\r'),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn span(source: &str, offset: usize, length: usize, column: usize) -> Span {
+        Span {
+            offset,
+            column,
+            length,
+            source: Some(Rc::from(source)),
+        }
+    }
+
+    #[test]
+    fn test_snippet_underlines_the_span() {
+        let source = "var a = 1;\nprint a + nil;\n";
+
+        assert_eq!(
+            snippet(&span(source, 19, 1, 9)).unwrap(),
+            " --> line 2, column 9\n  |\n2 | print a + nil;\n  |         ^"
+        );
+    }
+
+    #[test]
+    fn test_snippet_underlines_every_character_of_the_span() {
+        let source = "print \"é\" + nil;";
+
+        assert_eq!(
+            snippet(&span(source, 6, 4, 7)).unwrap(),
+            " --> line 1, column 7\n  |\n1 | print \"é\" + nil;\n  |       ^^^"
+        );
     }
 
-    pub fn report(&mut self, line: usize, where_: &str, message: &str) {
-        eprintln!("[line {}] Error{}: {}", line, where_, message);
-        self.had_error = true;
+    #[test]
+    fn test_snippet_keeps_tabs_in_the_indent() {
+        let source = "{\n\tprint -nil;\n}";
+
+        assert_eq!(
+            snippet(&span(source, 9, 1, 8)).unwrap(),
+            " --> line 2, column 8\n  |\n2 | \tprint -nil;\n  | \t      ^"
+        );
+    }
+
+    #[test]
+    fn test_snippet_stops_at_the_end_of_the_line() {
+        let source = "var s = \"one\ntwo\";";
+
+        assert_eq!(
+            snippet(&span(source, 8, 10, 9)).unwrap(),
+            " --> line 1, column 9\n  |\n1 | var s = \"one\n  |         ^^^^"
+        );
     }
 
-    pub fn report_runtime_error(&mut self, error: RuntimeError) {
-        let (message, token) = error.get_error();
-        eprintln!("Runtime Error - [line {}]: {}", token.line, message);
+    #[test]
+    fn test_traceback_lists_frames_innermost_first() {
+        let backtrace = vec![
+            CallFrame {
+                function: "inner".to_string(),
+                class: None,
+                line: 7,
+            },
+            CallFrame {
+                function: "run".to_string(),
+                class: Some("Job".to_string()),
+                line: 12,
+            },
+        ];
 
-        self.had_error = true;
+        assert_eq!(
+            traceback(3, &backtrace).unwrap(),
+            "Traceback (most recent call first):\n  [line 3] in inner()\n  [line 7] in Job.run()\n  [line 12] in script"
+        );
     }
 
-    pub fn report_parse_error(&mut self, error: ParseError) {
-        let (message, token) = error.get_error();
-        eprintln!(
-            "Parse Error - [line {}] Error at: {:?} - {}",
-            token.line,
-            token.lexeme,
-            // token.token_type,
-            message
+    #[test]
+    fn test_traceback_collapses_recursion() {
+        let mut backtrace: Vec<CallFrame> = (0..5)
+            .map(|_| CallFrame {
+                function: "count".to_string(),
+                class: None,
+                line: 4,
+            })
+            .collect();
+        backtrace.push(CallFrame {
+            function: "count".to_string(),
+            class: None,
+            line: 9,
+        });
+
+        assert_eq!(
+            traceback(4, &backtrace).unwrap(),
+            "Traceback (most recent call first):\n  [line 4] in count()\n  [line 4] in count()\n  [line 4] in count()\n  [Previous line repeated 3 more times]\n  [line 9] in script"
         );
-        self.had_error = true;
+    }
+
+    #[test]
+    fn test_error_at_the_top_level_has_no_traceback() {
+        assert!(traceback(3, &[]).is_none());
+    }
+
+    #[test]
+    fn test_span_without_source_has_no_snippet() {
+        assert!(snippet(&Span::default()).is_none());
     }
 }
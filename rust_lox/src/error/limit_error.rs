@@ -0,0 +1,27 @@
+use std::{fmt, time::Duration};
+
+#[derive(Debug, Clone)]
+/// # LimitError
+///
+/// Raised when a script uses up the fuel or the time it was given. Scripts cannot catch it, so it always ends the run.
+pub enum LimitError {
+    /// The script took more steps than the fuel it was given.
+    OutOfFuel(u64),
+    /// The script ran for longer than its timeout.
+    TimedOut(Duration),
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::OutOfFuel(fuel) => {
+                write!(f, "Out of fuel: the script took more than {} steps.", fuel)
+            }
+            LimitError::TimedOut(timeout) => {
+                write!(f, "Timed out: the script ran for more than {:?}.", timeout)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
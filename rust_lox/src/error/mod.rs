@@ -1,11 +1,14 @@
+pub mod conversion_error;
+pub mod diagnostic;
 pub mod error;
-pub mod lox_return;
+pub mod limit_error;
 pub mod parse_error;
 pub mod runtime_error;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum LoxError {
     ParseError(parse_error::ParseError),
     RuntimeError(runtime_error::RuntimeError),
-    LoxReturn(lox_return::LoxReturn),
+    LimitError(limit_error::LimitError),
 }
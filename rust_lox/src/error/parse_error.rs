@@ -5,7 +5,8 @@ use crate::grammar::token::Token;
 #[derive(Debug, Clone)]
 pub struct ParseError {
     message: String,
-    token: Token,
+    /// Boxed, like the token of a runtime error, so that `LoxError` stays small.
+    token: Box<Token>,
 }
 
 /// # when a parse error occurs the parser will continue parsing the rest of the tokens
@@ -13,12 +14,12 @@ impl ParseError {
     pub fn new(message: &str, token: &Token) -> Self {
         Self {
             message: String::from(message),
-            token: token.clone(),
+            token: Box::new(token.clone()),
         }
     }
 
     pub fn get_error(&self) -> (&str, &Token) {
-        (&self.message, &self.token)
+        (&self.message, &*self.token)
     }
 }
 
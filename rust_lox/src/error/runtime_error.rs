@@ -1,25 +1,81 @@
-use crate::grammar::token::Token;
+use crate::grammar::{object::Object, token::Token};
 use std::fmt;
 
 #[derive(Debug)]
 /// # RuntimeError
 ///
 /// Runtime errors get reported by the interpreter when it encounters an error during runtime.
+///
+/// A `throw` statement also travels as a runtime error. It carries the thrown value, which is what a catch clause receives.
 pub struct RuntimeError {
     message: String,
-    token: Token,
+    /// The token, the thrown value and the backtrace are boxed. Every fallible call returns a `Result` with this error in it, and keeping it small keeps those results cheap to move.
+    token: Box<Token>,
+    value: Option<Box<Object>>,
+    /// The calls that were running when the error left the innermost function, innermost first. Empty for errors raised at the top level.
+    backtrace: Box<[CallFrame]>,
 }
 
 impl RuntimeError {
     pub fn new(message: String, token: &Token) -> Self {
         Self {
             message,
-            token: token.clone(),
+            token: Box::new(token.clone()),
+            value: None,
+            backtrace: Box::default(),
+        }
+    }
+
+    pub fn thrown(value: Object, token: &Token) -> Self {
+        Self {
+            message: format!("Uncaught exception: {}", value),
+            token: Box::new(token.clone()),
+            value: Some(Box::new(value)),
+            backtrace: Box::default(),
         }
     }
 
     pub fn get_error(&self) -> (&str, &Token) {
-        (self.message.as_str(), &self.token)
+        (self.message.as_str(), &*self.token)
+    }
+
+    /// # thrown_value
+    /// The value passed to `throw`, or None for errors raised by the interpreter itself.
+    pub fn thrown_value(&self) -> Option<&Object> {
+        self.value.as_deref()
+    }
+
+    pub fn backtrace(&self) -> &[CallFrame] {
+        &self.backtrace
+    }
+
+    pub fn set_backtrace(&mut self, backtrace: Vec<CallFrame>) {
+        self.backtrace = backtrace.into_boxed_slice();
+    }
+}
+
+#[derive(Debug, Clone)]
+/// # CallFrame
+/// A Lox function call in progress.
+///
+/// - function
+///   - The name of the function
+/// - class
+///   - The class the function is a method of
+/// - line
+///   - The line the function was called from
+pub struct CallFrame {
+    pub function: String,
+    pub class: Option<String>,
+    pub line: usize,
+}
+
+impl fmt::Display for CallFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.class {
+            Some(class) => write!(f, "{}.{}()", class, self.function),
+            None => write!(f, "{}()", self.function),
+        }
     }
 }
 
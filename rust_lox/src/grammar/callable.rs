@@ -1,33 +1,74 @@
-use crate::{error::LoxError, interpreter::Interpreter};
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    error::{runtime_error::RuntimeError, LoxError},
+    interpreter::Interpreter,
+};
 
 use super::{
-    class::LoxClass,
-    function::LoxFunction,
-    native_function::{Clock, LoxNativeFunctions},
-    object::Object,
+    class::LoxClass, function::LoxFunction, instance::LoxInstance,
+    native_function::LoxNativeFunction, object::Object, token::Token,
 };
 
 #[derive(Debug, Clone)]
 pub enum Callable {
     LoxFunction(LoxFunction),
     LoxClass(LoxClass),
-    LoxNativeFunction(LoxNativeFunctions),
+    LoxNativeFunction(LoxNativeFunction),
 }
 
 impl Callable {
+    /// # is_same
+    /// Callables compare by identity.
+    pub fn is_same(&self, other: &Callable) -> bool {
+        match (self, other) {
+            (Callable::LoxFunction(a), Callable::LoxFunction(b)) => a.is_same(b),
+            (Callable::LoxClass(a), Callable::LoxClass(b)) => a.id() == b.id(),
+            (Callable::LoxNativeFunction(a), Callable::LoxNativeFunction(b)) => a.is_same(b),
+            _ => false,
+        }
+    }
+
+    /// # arity
+    /// The number of arguments the callable expects. A class expects as many as its initializer.
+    pub fn arity(&self) -> u8 {
+        match self {
+            Callable::LoxFunction(f) => f.arity(),
+            Callable::LoxClass(c) => c.arity(),
+            Callable::LoxNativeFunction(native) => native.arity(),
+        }
+    }
+
+    /// # call
+    /// The paren token of the call expression is used to report errors raised by native functions, and a call that would go deeper than the interpreter's maximum call depth.
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
         arguments: Vec<Object>,
+        paren: &Token,
     ) -> Result<Object, LoxError> {
-        match self {
-            Callable::LoxFunction(f) => f.call(interpreter, arguments),
-            Callable::LoxClass(c) => c.call(interpreter, arguments),
-            Callable::LoxNativeFunction(c) => match c {
-                // list of all native functions here
-                LoxNativeFunctions::Clock(Clock) => Clock::new().call(interpreter, vec![]),
-            },
+        // natives take no frame, and anything they call back into Lox comes through here again
+        if matches!(self, Callable::LoxFunction(_) | Callable::LoxClass(_)) {
+            interpreter.check_call_depth(paren)?;
         }
+
+        let native_result = match self {
+            Callable::LoxFunction(f) => return f.call(interpreter, arguments),
+            Callable::LoxClass(c) => match c.find_native_method("init") {
+                // a native initializer reports errors like any other native
+                Some(initializer) => {
+                    let instance = LoxInstance::new(Rc::new(RefCell::new(c.clone())));
+                    initializer
+                        .bind(instance.clone())
+                        .call(interpreter, arguments)
+                        .map(|_| Object::Instance(instance))
+                }
+                None => return c.call(interpreter, arguments),
+            },
+            Callable::LoxNativeFunction(native) => native.call(interpreter, arguments),
+        };
+
+        native_result.map_err(|message| LoxError::RuntimeError(RuntimeError::new(message, paren)))
     }
 }
 
@@ -1,12 +1,15 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use crate::{
+    environment::generate_object_id,
     error::LoxError,
     grammar::{callable::LoxCallable, object::Object},
     interpreter::Interpreter,
 };
 
-use super::{callable::Callable, function::LoxFunction, instance::LoxInstance};
+use super::{
+    callable::Callable, function::LoxFunction, instance::LoxInstance, native_function::NativeMethod,
+};
 
 #[derive(Debug, Clone)]
 /// ## LoxClass
@@ -16,13 +19,24 @@ use super::{callable::Callable, function::LoxFunction, instance::LoxInstance};
 ///   - The name of the class
 /// - methods
 ///   - A hashmap of methods that the class has
+/// - setters
+///   - A hashmap of setter methods, declared with `set name(value) {}`. They are kept apart from methods so a getter and a setter can share a name
+/// - static_methods
+///   - A hashmap of methods that are called on the class itself, declared with `class name() {}`
+/// - native_methods
+///   - A hashmap of methods implemented in Rust. Only classes defined by the host have them
 /// - superclass
 ///   - The superclass of the class
 ///   - With super expressions, the superclass is a fixed property of the class declaration itself. Every time you evaluate some super expression, the superclass is always the same.
 pub struct LoxClass {
     name: String,
     methods: HashMap<String, Object>,
+    setters: HashMap<String, Object>,
+    static_methods: HashMap<String, Object>,
+    native_methods: HashMap<String, NativeMethod>,
     superclass: Option<Box<LoxClass>>,
+    /// Classes are copied along with their method tables, so the id is what tells two classes apart.
+    id: usize,
 }
 
 impl LoxClass {
@@ -30,11 +44,21 @@ impl LoxClass {
         name: String,
         superclass: Option<LoxClass>,
         methods: Option<HashMap<String, Object>>,
+        setters: Option<HashMap<String, Object>>,
+        static_methods: Option<HashMap<String, Object>>,
     ) -> Self {
         let methods = match methods {
             Some(methods) => methods,
             None => HashMap::new(),
         };
+        let setters = match setters {
+            Some(setters) => setters,
+            None => HashMap::new(),
+        };
+        let static_methods = match static_methods {
+            Some(static_methods) => static_methods,
+            None => HashMap::new(),
+        };
         let superclass: Option<Box<LoxClass>> = match superclass {
             Some(superclass) => Some(Box::new(superclass)),
             None => None,
@@ -43,14 +67,34 @@ impl LoxClass {
         Self {
             name,
             methods,
+            setters,
+            static_methods,
+            native_methods: HashMap::new(),
             superclass,
+            id: generate_object_id(),
         }
     }
 
+    /// # native
+    /// A class defined from Rust. Scripts can call it, subclass it and override its methods like any other class.
+    pub fn native(name: String, methods: Vec<NativeMethod>) -> Self {
+        let mut class = Self::new(name, None, None, None, None);
+        class.native_methods = methods
+            .into_iter()
+            .map(|method| (method.name().to_string(), method))
+            .collect();
+
+        class
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
     pub fn find_method(&self, name: &str) -> Option<LoxFunction> {
         match self.methods.get(name).cloned() {
             Some(method) => match method {
@@ -60,6 +104,8 @@ impl LoxClass {
                 },
                 _ => None,
             },
+            // a native method here overrides any method further up
+            None if self.native_methods.contains_key(name) => None,
             None => {
                 if let Some(superclass) = self.superclass.clone() {
                     superclass.find_method(name)
@@ -71,11 +117,58 @@ impl LoxClass {
     }
 }
 
+impl LoxClass {
+    /// # find_native_method
+    /// The native counterpart of find_method. A Lox method with the name in a subclass hides the native one.
+    pub fn find_native_method(&self, name: &str) -> Option<NativeMethod> {
+        if self.methods.contains_key(name) {
+            return None;
+        }
+
+        match self.native_methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => match &self.superclass {
+                Some(superclass) => superclass.find_native_method(name),
+                None => None,
+            },
+        }
+    }
+
+    /// # find_setter
+    /// Setters are inherited like methods.
+    pub fn find_setter(&self, name: &str) -> Option<LoxFunction> {
+        match self.setters.get(name) {
+            Some(Object::Callable(Callable::LoxFunction(func))) => Some(func.clone()),
+            Some(_) => None,
+            None => match &self.superclass {
+                Some(superclass) => superclass.find_setter(name),
+                None => None,
+            },
+        }
+    }
+
+    /// # find_static_method
+    /// Static methods are inherited, so the lookup walks up the superclass chain like find_method does.
+    pub fn find_static_method(&self, name: &str) -> Option<LoxFunction> {
+        match self.static_methods.get(name) {
+            Some(Object::Callable(Callable::LoxFunction(func))) => Some(func.clone()),
+            Some(_) => None,
+            None => match &self.superclass {
+                Some(superclass) => superclass.find_static_method(name),
+                None => None,
+            },
+        }
+    }
+}
+
 impl LoxCallable<Result<Object, LoxError>> for LoxClass {
     fn arity(&self) -> u8 {
         match self.find_method("init") {
             Some(method) => method.arity(),
-            None => 0,
+            None => match self.find_native_method("init") {
+                Some(method) => method.arity(),
+                None => 0,
+            },
         }
     }
 
@@ -87,7 +180,12 @@ impl LoxCallable<Result<Object, LoxError>> for LoxClass {
         let instance = LoxInstance::new(Rc::new(RefCell::new(self.clone())));
 
         if let Some(mut initializer) = self.find_method("init") {
-            initializer.bind(instance).call(interpreter, arguments)
+            let initializer = initializer.bind(instance);
+            let frame = initializer.call_frame(interpreter.call_site_line());
+
+            interpreter.in_frame(frame, |interpreter| {
+                initializer.execute(interpreter, arguments)
+            })
         } else {
             Ok(Object::Instance(instance).clone())
         }
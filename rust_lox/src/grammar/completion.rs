@@ -0,0 +1,14 @@
+use super::object::Object;
+
+#[derive(Debug, Clone)]
+/// # Completion
+/// How a statement finished running.
+///
+/// `return`, `break` and `continue` end every statement around them until they reach the function or loop that handles them. They travel outwards as completions so that the error channel only carries real errors.
+pub enum Completion {
+    Normal,
+    /// Boxed so that the other completions, which are far more common, stay small.
+    Return(Box<Object>),
+    Break,
+    Continue,
+}
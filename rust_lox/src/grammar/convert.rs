@@ -0,0 +1,357 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::{
+    map::{LoxMap, MapKey},
+    object::Object,
+};
+use crate::error::conversion_error::ConversionError;
+
+/// # IntoLox
+/// Converts a Rust value into a Lox value. Vectors become lists and hash maps become maps, both newly allocated.
+pub trait IntoLox {
+    fn into_lox(self) -> Object;
+}
+
+/// # FromLox
+/// Converts a Lox value into a Rust value. A value of the wrong type is a `ConversionError`. It becomes a runtime error at the call when a native function returns it, and a host that converts a value it got from Lox can turn it into one with `ConversionError::at`.
+///
+/// Lists and maps are copied, so changes to the Rust value do not show up in Lox.
+pub trait FromLox: Sized {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError>;
+}
+
+fn expected(what: &str, value: &Object) -> ConversionError {
+    ConversionError::new(format!("Expected {} but got {}.", what, value.type_name()))
+}
+
+impl IntoLox for Object {
+    fn into_lox(self) -> Object {
+        self
+    }
+}
+
+impl FromLox for Object {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> Object {
+        Object::Nil
+    }
+}
+
+impl IntoLox for bool {
+    fn into_lox(self) -> Object {
+        Object::Bool(self)
+    }
+}
+
+/// Only booleans convert. Use `Object::is_truthy` for Lox truthiness.
+impl FromLox for bool {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+        match value {
+            Object::Bool(boolean) => Ok(*boolean),
+            _ => Err(expected("a boolean", value)),
+        }
+    }
+}
+
+impl IntoLox for f64 {
+    fn into_lox(self) -> Object {
+        Object::Num(self)
+    }
+}
+
+impl FromLox for f64 {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+        match value {
+            Object::Num(number) => Ok(*number),
+            _ => Err(expected("a number", value)),
+        }
+    }
+}
+
+impl IntoLox for f32 {
+    fn into_lox(self) -> Object {
+        Object::Num(self as f64)
+    }
+}
+
+impl FromLox for f32 {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+        f64::from_lox(value).map(|number| number as f32)
+    }
+}
+
+/// Lox numbers are f64, so integers convert only from whole numbers in the integer type's range.
+macro_rules! integer_conversions {
+    ($($integer:ty),*) => {
+        $(
+            impl IntoLox for $integer {
+                fn into_lox(self) -> Object {
+                    Object::Num(self as f64)
+                }
+            }
+
+            impl FromLox for $integer {
+                fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+                    let number = f64::from_lox(value).map_err(|_| expected("an integer", value))?;
+
+                    if number.fract() != 0.0 || !number.is_finite() {
+                        return Err(ConversionError::new(format!(
+                            "Expected an integer but got {}.",
+                            number
+                        )));
+                    }
+                    // MAX + 1 is a power of two, so unlike MAX it is exact as an f64
+                    if number < <$integer>::MIN as f64 || number >= <$integer>::MAX as f64 + 1.0 {
+                        return Err(ConversionError::new(format!(
+                            "Expected an integer between {} and {} but got {}.",
+                            <$integer>::MIN,
+                            <$integer>::MAX,
+                            number
+                        )));
+                    }
+
+                    Ok(number as $integer)
+                }
+            }
+        )*
+    };
+}
+
+integer_conversions!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl IntoLox for String {
+    fn into_lox(self) -> Object {
+        Object::Str(self)
+    }
+}
+
+impl IntoLox for &str {
+    fn into_lox(self) -> Object {
+        Object::Str(self.to_string())
+    }
+}
+
+impl FromLox for String {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+        match value {
+            Object::Str(string) => Ok(string.clone()),
+            _ => Err(expected("a string", value)),
+        }
+    }
+}
+
+/// None is nil.
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> Object {
+        match self {
+            Some(value) => value.into_lox(),
+            None => Object::Nil,
+        }
+    }
+}
+
+impl<T: FromLox> FromLox for Option<T> {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+        match value {
+            Object::Nil => Ok(None),
+            _ => T::from_lox(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoLox> IntoLox for Vec<T> {
+    fn into_lox(self) -> Object {
+        let elements = self.into_iter().map(IntoLox::into_lox).collect();
+
+        Object::List(Rc::new(RefCell::new(elements)))
+    }
+}
+
+impl<T: FromLox> FromLox for Vec<T> {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+        let Object::List(list) = value else {
+            return Err(expected("a list", value));
+        };
+
+        list.borrow()
+            .iter()
+            .enumerate()
+            .map(|(index, element)| {
+                T::from_lox(element)
+                    .map_err(|error| error.context(&format!("List element {}", index)))
+            })
+            .collect()
+    }
+}
+
+impl<T: IntoLox> IntoLox for HashMap<String, T> {
+    fn into_lox(self) -> Object {
+        let mut map = LoxMap::new();
+        for (key, value) in self {
+            map.insert(MapKey::Str(key), value.into_lox());
+        }
+
+        Object::Map(Rc::new(RefCell::new(map)))
+    }
+}
+
+/// Every key of the map has to be a string.
+impl<T: FromLox> FromLox for HashMap<String, T> {
+    fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+        let Object::Map(map) = value else {
+            return Err(expected("a map", value));
+        };
+        let map = map.borrow();
+
+        map.keys()
+            .into_iter()
+            .zip(map.values())
+            .map(|(key, value)| {
+                let key = match key {
+                    Object::Str(key) => key,
+                    _ => {
+                        return Err(ConversionError::new(format!(
+                            "Expected string map keys but got {}.",
+                            key.type_name()
+                        )))
+                    }
+                };
+                let value = T::from_lox(&value)
+                    .map_err(|error| error.context(&format!("Map entry \"{}\"", key)))?;
+
+                Ok((key, value))
+            })
+            .collect()
+    }
+}
+
+/// # field
+/// Reads a field of an instance, or the value of a string key in a map, and converts it. Implementations of `FromLox` for the host's own structs can read their fields with it.
+pub fn field<T: FromLox>(value: &Object, name: &str) -> Result<T, ConversionError> {
+    let field = match value {
+        Object::Instance(instance) => instance.fields.borrow().get(name).cloned(),
+        Object::Map(map) => map.borrow().get(&MapKey::Str(name.to_string())).cloned(),
+        _ => return Err(expected("an instance or a map", value)),
+    };
+
+    match field {
+        Some(field) => {
+            T::from_lox(&field).map_err(|error| error.context(&format!("Field '{}'", name)))
+        }
+        None => Err(ConversionError::new(format!("Missing field '{}'.", name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mismatch<T>(message: &str) -> Result<T, ConversionError> {
+        Err(ConversionError::new(message.to_string()))
+    }
+
+    #[test]
+    fn test_round_trips() {
+        assert_eq!(f64::from_lox(&2.5.into_lox()), Ok(2.5));
+        assert_eq!(i64::from_lox(&(-3i64).into_lox()), Ok(-3));
+        assert_eq!(bool::from_lox(&true.into_lox()), Ok(true));
+        assert_eq!(String::from_lox(&"lox".into_lox()), Ok("lox".to_string()));
+        assert_eq!(Option::<f64>::from_lox(&None::<f64>.into_lox()), Ok(None));
+        assert_eq!(Option::<u8>::from_lox(&Some(7u8).into_lox()), Ok(Some(7)));
+        assert_eq!(
+            Vec::<String>::from_lox(&vec!["a", "b"].into_lox()),
+            Ok(vec!["a".to_string(), "b".to_string()])
+        );
+
+        let scores = HashMap::from([("ada".to_string(), 3u32), ("bob".to_string(), 5)]);
+        assert_eq!(
+            HashMap::<String, u32>::from_lox(&scores.clone().into_lox()),
+            Ok(scores)
+        );
+    }
+
+    #[test]
+    fn test_type_mismatches() {
+        assert_eq!(
+            f64::from_lox(&Object::Str("1".to_string())),
+            mismatch("Expected a number but got string.")
+        );
+        assert_eq!(
+            bool::from_lox(&Object::Nil),
+            mismatch("Expected a boolean but got nil.")
+        );
+        assert_eq!(
+            i32::from_lox(&Object::Num(1.5)),
+            mismatch("Expected an integer but got 1.5.")
+        );
+        assert_eq!(
+            u8::from_lox(&Object::Num(-1.0)),
+            mismatch("Expected an integer between 0 and 255 but got -1.")
+        );
+        assert_eq!(
+            Vec::<f64>::from_lox(&vec![1.0.into_lox(), true.into_lox()].into_lox()),
+            mismatch("List element 1: Expected a number but got boolean.")
+        );
+
+        let mut numbered = LoxMap::new();
+        numbered.insert(
+            MapKey::from_object(&Object::Num(1.0)).unwrap(),
+            "one".into_lox(),
+        );
+        assert_eq!(
+            HashMap::<String, String>::from_lox(&Object::Map(Rc::new(RefCell::new(numbered)))),
+            mismatch("Expected string map keys but got number.")
+        );
+    }
+
+    #[test]
+    fn test_conversion_error_becomes_a_runtime_error() {
+        use crate::grammar::token::{Token, TokenType};
+
+        let token = Token::new(TokenType::Identifier, "x".to_string(), None, 7, 0);
+        let error = f64::from_lox(&Object::Nil).unwrap_err().at(&token);
+
+        assert_eq!(error.get_error().0, "Expected a number but got nil.");
+        assert_eq!(error.get_error().1.line, 7);
+    }
+
+    #[test]
+    fn test_integer_range() {
+        assert_eq!(
+            i64::from_lox(&Object::Num(-9223372036854775808.0)),
+            Ok(i64::MIN)
+        );
+        assert!(i64::from_lox(&Object::Num(9223372036854775808.0)).is_err());
+        assert!(u64::from_lox(&Object::Num(f64::INFINITY)).is_err());
+        assert!(usize::from_lox(&Object::Num(f64::NAN)).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    impl FromLox for Point {
+        fn from_lox(value: &Object) -> Result<Self, ConversionError> {
+            Ok(Point {
+                x: field(value, "x")?,
+                y: field(value, "y")?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_struct_from_map() {
+        let point = HashMap::from([("x".to_string(), 1.0), ("y".to_string(), 2.0)]).into_lox();
+        assert_eq!(Point::from_lox(&point), Ok(Point { x: 1.0, y: 2.0 }));
+
+        let point = HashMap::from([("x".to_string(), 1.0)]).into_lox();
+        assert_eq!(Point::from_lox(&point), mismatch("Missing field 'y'."));
+    }
+}
@@ -1,6 +1,6 @@
 use super::object::Object;
+use super::stmt::FunStmt;
 use super::token::Token;
-use std::hash::{Hash, Hasher};
 
 #[derive(Debug, Clone)]
 pub struct Variable {
@@ -38,6 +38,42 @@ pub enum Expr {
     Grouping {
         expression: Box<Expr>,
     },
+    /// ## Index
+    /// Reads an element out of a list, `xs[i]`. The bracket token is the closing `]`, which is used for error reporting.
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    /// ## IndexSet
+    /// Writes an element of a list, `xs[i] = value`. Like Set, it is produced by the parser when an Index expression is the target of an assignment.
+    IndexSet {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+    /// ## Lambda
+    /// An anonymous function, `fun (a, b) { ... }`, that can be used anywhere an expression can, such as a call argument.
+    Lambda(FunStmt),
+    /// ## Interpolation
+    /// An interpolated string, `"Hello ${name}!"`. The parts alternate between string literals and interpolated expressions, and are joined with the same formatting `print` uses.
+    Interpolation {
+        quote: Token,
+        parts: Vec<Expr>,
+    },
+    /// ## Map
+    /// A map literal, `{"a": 1, "b": 2}`. Each entry pairs a key expression with a value expression.
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
+    /// ## List
+    /// A list literal, `[a, b, c]`.
+    List {
+        bracket: Token,
+        elements: Vec<Expr>,
+    },
     Literal {
         value: Option<Object>,
     },
@@ -55,9 +91,11 @@ pub enum Expr {
         name: Token,
         value: Box<Expr>,
     },
+    /// ## Super
+    /// `super.method`. The method name is boxed, because with two tokens inline this variant was twice the size of any other.
     Super {
         keyword: Token,
-        method: Token,
+        method: Box<Token>,
     },
     This {
         keyword: Token,
@@ -81,6 +119,21 @@ impl Expr {
             } => visitor.visit_call_expr(callee, paren, arguments),
             Expr::Get { object, name } => visitor.visit_get_expr(object, name),
             Expr::Grouping { expression } => visitor.visit_grouping_expr(expression),
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => visitor.visit_index_expr(object, bracket, index),
+            Expr::IndexSet {
+                object,
+                bracket,
+                index,
+                value,
+            } => visitor.visit_index_set_expr(object, bracket, index, value),
+            Expr::Lambda(declaration) => visitor.visit_lambda_expr(declaration),
+            Expr::Interpolation { quote, parts } => visitor.visit_interpolation_expr(quote, parts),
+            Expr::Map { brace, entries } => visitor.visit_map_expr(brace, entries),
+            Expr::List { bracket, elements } => visitor.visit_list_expr(bracket, elements),
             Expr::Literal { value } => visitor.visit_literal_expr(value),
             Expr::Logical {
                 left,
@@ -88,14 +141,14 @@ impl Expr {
                 right,
             } => visitor.visit_logical_expr(left, operator, right),
             Expr::Unary { operator, right } => visitor.visit_unary_expr(operator, right),
-            Expr::This { keyword } => visitor.visit_this_expr(&self, keyword),
+            Expr::This { keyword } => visitor.visit_this_expr(keyword),
             Expr::Set {
                 object,
                 name,
                 value,
             } => visitor.visit_set_expr(object, name, value),
-            Expr::Super { keyword, method } => visitor.visit_super_expr(&self, keyword, method),
-            Expr::Variable(Variable { name }) => visitor.visit_variable_expr(&self, name),
+            Expr::Super { keyword, method } => visitor.visit_super_expr(keyword, method),
+            Expr::Variable(Variable { name }) => visitor.visit_variable_expr(name),
         }
     }
 }
@@ -106,18 +159,30 @@ pub trait ExprVisitor<R> {
     fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &Vec<Expr>) -> R;
     fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> R;
     fn visit_grouping_expr(&mut self, expression: &Expr) -> R;
+    fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> R;
+    fn visit_index_set_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> R;
+    fn visit_lambda_expr(&mut self, declaration: &FunStmt) -> R;
+    fn visit_interpolation_expr(&mut self, quote: &Token, parts: &[Expr]) -> R;
+    fn visit_list_expr(&mut self, bracket: &Token, elements: &[Expr]) -> R;
+    fn visit_map_expr(&mut self, brace: &Token, entries: &[(Expr, Expr)]) -> R;
     fn visit_literal_expr(&mut self, value: &Option<Object>) -> R;
     fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> R;
     fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> R;
     fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> R;
-    fn visit_this_expr(&mut self, expr: &Expr, keyword: &Token) -> R;
-    fn visit_super_expr(&mut self, expr: &Expr, keyword: &Token, method: &Token) -> R;
-    fn visit_variable_expr(&mut self, expr: &Expr, name: &Token) -> R;
+    fn visit_this_expr(&mut self, keyword: &Token) -> R;
+    fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> R;
+    fn visit_variable_expr(&mut self, name: &Token) -> R;
 }
 
 impl PartialEq for Expr {
     fn eq(&self, other: &Self) -> bool {
-        // Implement structural equality comparison
+        // Structural equality. Tokens are compared by lexeme, so the same code written on two different lines is equal
         match (self, other) {
             (
                 Expr::Assign {
@@ -161,6 +226,76 @@ impl PartialEq for Expr {
                     expression: expression2,
                 },
             ) => expression1 == expression2,
+            (
+                Expr::Index {
+                    object: object1,
+                    bracket: bracket1,
+                    index: index1,
+                },
+                Expr::Index {
+                    object: object2,
+                    bracket: bracket2,
+                    index: index2,
+                },
+            ) => object1 == object2 && bracket1.lexeme == bracket2.lexeme && index1 == index2,
+            (
+                Expr::IndexSet {
+                    object: object1,
+                    bracket: bracket1,
+                    index: index1,
+                    value: value1,
+                },
+                Expr::IndexSet {
+                    object: object2,
+                    bracket: bracket2,
+                    index: index2,
+                    value: value2,
+                },
+            ) => {
+                object1 == object2
+                    && bracket1.lexeme == bracket2.lexeme
+                    && index1 == index2
+                    && value1 == value2
+            }
+            (Expr::Lambda(declaration1), Expr::Lambda(declaration2)) => {
+                declaration1.name.lexeme == declaration2.name.lexeme
+                    && declaration1.params.len() == declaration2.params.len()
+                    && declaration1
+                        .params
+                        .iter()
+                        .zip(declaration2.params.iter())
+                        .all(|(param1, param2)| param1.lexeme == param2.lexeme)
+            }
+            (
+                Expr::Interpolation {
+                    quote: quote1,
+                    parts: parts1,
+                },
+                Expr::Interpolation {
+                    quote: quote2,
+                    parts: parts2,
+                },
+            ) => quote1.lexeme == quote2.lexeme && parts1 == parts2,
+            (
+                Expr::Map {
+                    brace: brace1,
+                    entries: entries1,
+                },
+                Expr::Map {
+                    brace: brace2,
+                    entries: entries2,
+                },
+            ) => brace1.lexeme == brace2.lexeme && entries1 == entries2,
+            (
+                Expr::List {
+                    bracket: bracket1,
+                    elements: elements1,
+                },
+                Expr::List {
+                    bracket: bracket2,
+                    elements: elements2,
+                },
+            ) => bracket1.lexeme == bracket2.lexeme && elements1 == elements2,
             (Expr::Literal { value: value1 }, Expr::Literal { value: value2 }) => {
                 match (value1, value2) {
                     (Some(obj1), Some(obj2)) => match (obj1, obj2) {
@@ -200,7 +335,7 @@ impl PartialEq for Expr {
             (
                 Expr::Variable(Variable { name: name1 }),
                 Expr::Variable(Variable { name: name2 }),
-            ) => name1 == name2,
+            ) => name1.lexeme == name2.lexeme,
             (
                 Expr::Get {
                     object: object1,
@@ -210,7 +345,7 @@ impl PartialEq for Expr {
                     object: object2,
                     name: name2,
                 },
-            ) => object1 == object2 && name1 == name2,
+            ) => object1 == object2 && name1.lexeme == name2.lexeme,
             (
                 Expr::Set {
                     object: object1,
@@ -222,7 +357,7 @@ impl PartialEq for Expr {
                     name: name2,
                     value: value2,
                 },
-            ) => object1 == object2 && name1 == name2 && value1 == value2,
+            ) => object1 == object2 && name1.lexeme == name2.lexeme && value1 == value2,
             (
                 Expr::Super {
                     keyword: keyword1,
@@ -232,106 +367,21 @@ impl PartialEq for Expr {
                     keyword: keyword2,
                     method: method2,
                 },
-            ) => keyword1 == keyword2 && method1 == method2,
+            ) => keyword1.lexeme == keyword2.lexeme && method1.lexeme == method2.lexeme,
             (Expr::This { keyword: keyword1 }, Expr::This { keyword: keyword2 }) => {
-                keyword1 == keyword2
+                keyword1.lexeme == keyword2.lexeme
             }
             _ => false,
         }
     }
 }
 
-impl Eq for Expr {}
-
-impl Hash for Expr {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        match self {
-            Expr::Assign { name, value } => {
-                name.lexeme.hash(state);
-                value.hash(state);
-            }
-            Expr::Binary {
-                left,
-                operator,
-                right,
-            } => {
-                left.hash(state);
-                operator.lexeme.hash(state);
-                right.hash(state);
-            }
-            Expr::Call {
-                callee,
-                paren,
-                arguments,
-            } => {
-                callee.hash(state);
-                paren.lexeme.hash(state);
-                arguments.hash(state);
-            }
-            Expr::Get { object, name } => {
-                object.hash(state);
-                name.lexeme.hash(state);
-            }
-            Expr::Grouping { expression } => {
-                expression.hash(state);
-            }
-            Expr::Literal { value } => {
-                match value {
-                    Some(obj) => match obj {
-                        Object::Bool(b) => b.hash(state),
-                        Object::Nil => (),
-                        Object::Num(n) => {
-                            state.write_u8(1); // Variant tag
-                                               // Convert f64 to a stable representation for hashing
-                                               // Note: This is a simple example; consider edge cases like NaN, Infinity, etc.
-                            n.to_bits().hash(state)
-                        }
-                        Object::Str(s) => s.hash(state),
-                        Object::Callable(_) => (),
-                        Object::Instance(_) => (),
-                    },
-                    None => (),
-                }
-            }
-            Expr::Logical {
-                left,
-                operator,
-                right,
-            } => {
-                left.hash(state);
-                operator.lexeme.hash(state);
-                right.hash(state);
-            }
-            Expr::This { keyword } => {
-                keyword.lexeme.hash(state);
-            }
-            Expr::Unary { operator, right } => {
-                operator.lexeme.hash(state);
-                right.hash(state);
-            }
-            Expr::Set {
-                object,
-                name,
-                value,
-            } => {
-                object.hash(state);
-                name.lexeme.hash(state);
-                value.hash(state);
-            }
-            Expr::Super { keyword, method } => {
-                keyword.hash(state);
-                method.hash(state);
-            }
-            Expr::Variable(Variable { name }) => {
-                name.hash(state);
-            }
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::{environment::generate_id, grammar::token::TokenType};
+    use crate::{
+        environment::generate_id,
+        grammar::token::{Span, TokenType},
+    };
 
     use super::*; // Import everything from the outer module
 
@@ -376,7 +426,8 @@ mod tests {
             lexeme: "+".to_string(),
             literal: None,
             line: 1,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
 
         let expr1 = Expr::Binary {
@@ -409,14 +460,16 @@ mod tests {
             lexeme: "+".to_string(),
             literal: None,
             line: 1,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
         let operator2 = Token {
             token_type: TokenType::Minus, // Adjust according to your TokenType definition
             lexeme: "-".to_string(),
             literal: None,
             line: 1,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
 
         let expr1 = Expr::Binary {
@@ -444,7 +497,8 @@ mod tests {
                 lexeme: "2".to_string(),
                 literal: Some(Object::Str("2".to_string())),
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
         });
         let var_expr_2 = Expr::Variable(Variable {
@@ -453,7 +507,8 @@ mod tests {
                 lexeme: "2".to_string(),
                 literal: Some(Object::Str("2".to_string())),
                 line: 500,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
         });
 
@@ -471,7 +526,8 @@ mod tests {
                 lexeme: "2".to_string(),
                 literal: Some(Object::Str("2".to_string())),
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
         });
         let var_expr_2 = Expr::Variable(Variable {
@@ -480,7 +536,8 @@ mod tests {
                 lexeme: "3".to_string(),
                 literal: Some(Object::Str("3".to_string())),
                 line: 500,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
         });
 
@@ -534,7 +591,8 @@ mod tests {
                 lexeme: "-".to_string(),
                 literal: None,
                 line: 1,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             right: Box::new(Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -546,7 +604,8 @@ mod tests {
                 lexeme: "-".to_string(),
                 literal: None,
                 line: 1,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             right: Box::new(Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -566,7 +625,8 @@ mod tests {
                 lexeme: "-".to_string(),
                 literal: None,
                 line: 1,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             right: Box::new(Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -578,7 +638,8 @@ mod tests {
                 lexeme: "-".to_string(),
                 literal: None,
                 line: 1,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             right: Box::new(Expr::Literal {
                 value: Some(Object::Num(24.0)),
@@ -603,7 +664,8 @@ mod tests {
             lexeme: "and".to_string(),
             literal: None,
             line: 1,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
 
         let expr1 = Expr::Logical {
@@ -636,14 +698,16 @@ mod tests {
             lexeme: "and".to_string(),
             literal: None,
             line: 1,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
         let operator2 = Token {
             token_type: TokenType::Or, // Adjust according to your TokenType definition
             lexeme: "or".to_string(),
             literal: None,
             line: 1,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
 
         let expr1 = Expr::Logical {
@@ -671,7 +735,8 @@ mod tests {
                 lexeme: "x".to_string(),
                 literal: Some(Object::Str("x".to_string())),
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             value: Box::new(Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -683,7 +748,8 @@ mod tests {
                 lexeme: "x".to_string(),
                 literal: Some(Object::Str("x".to_string())),
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             value: Box::new(Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -704,7 +770,8 @@ mod tests {
                 lexeme: "x".to_string(),
                 literal: Some(Object::Str("x".to_string())),
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             value: Box::new(Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -716,7 +783,8 @@ mod tests {
                 lexeme: "y".to_string(),
                 literal: Some(Object::Str("y".to_string())),
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             value: Box::new(Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -738,7 +806,8 @@ mod tests {
                     lexeme: "x".to_string(),
                     literal: Some(Object::Str("x".to_string())),
                     line: 200,
-                    _id: generate_id(),
+                    id: generate_id(),
+                    span: Span::default(),
                 },
             })),
             paren: Token {
@@ -746,7 +815,8 @@ mod tests {
                 lexeme: "(".to_string(),
                 literal: None,
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             arguments: vec![Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -759,7 +829,8 @@ mod tests {
                     lexeme: "x".to_string(),
                     literal: Some(Object::Str("x".to_string())),
                     line: 200,
-                    _id: generate_id(),
+                    id: generate_id(),
+                    span: Span::default(),
                 },
             })),
             paren: Token {
@@ -767,7 +838,8 @@ mod tests {
                 lexeme: "(".to_string(),
                 literal: None,
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             arguments: vec![Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -789,7 +861,8 @@ mod tests {
                     lexeme: "x".to_string(),
                     literal: Some(Object::Str("x".to_string())),
                     line: 200,
-                    _id: generate_id(),
+                    id: generate_id(),
+                    span: Span::default(),
                 },
             })),
             paren: Token {
@@ -797,7 +870,8 @@ mod tests {
                 lexeme: "(".to_string(),
                 literal: None,
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             arguments: vec![Expr::Literal {
                 value: Some(Object::Num(42.0)),
@@ -810,7 +884,8 @@ mod tests {
                     lexeme: "y".to_string(),
                     literal: Some(Object::Str("y".to_string())),
                     line: 200,
-                    _id: generate_id(),
+                    id: generate_id(),
+                    span: Span::default(),
                 },
             })),
             paren: Token {
@@ -818,7 +893,8 @@ mod tests {
                 lexeme: "(".to_string(),
                 literal: None,
                 line: 200,
-                _id: generate_id(),
+                id: generate_id(),
+                span: Span::default(),
             },
             arguments: vec![Expr::Literal {
                 value: Some(Object::Num(42.0)),
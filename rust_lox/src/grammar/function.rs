@@ -2,16 +2,13 @@ use std::{cell::RefCell, rc::Rc};
 
 use crate::{
     environment::{self, Environment},
-    error::LoxError,
-    grammar::{
-        callable::LoxCallable,
-        object::Object,
-        stmt::{BlockStmt, FunStmt},
-    },
+    error::{runtime_error::CallFrame, LoxError},
+    grammar::{callable::LoxCallable, object::Object, stmt::FunStmt},
     interpreter::Interpreter,
 };
 
 use super::{
+    completion::Completion,
     instance::LoxInstance,
     stmt::{FunType, Stmt},
     token::create_this_token,
@@ -23,6 +20,10 @@ pub struct LoxFunction {
     closure: Rc<RefCell<environment::Environment>>,
     is_initializer: bool,
     pub is_getter: bool,
+    /// The class a method belongs to, shown in stack traces.
+    class: Option<String>,
+    /// The global environment of the module the function was declared in. Unresolved names in the body are looked up there.
+    globals: Rc<RefCell<Environment>>,
 }
 
 impl LoxFunction {
@@ -30,15 +31,18 @@ impl LoxFunction {
         declaration: &FunStmt,
         closure: Rc<RefCell<Environment>>,
         is_initializer: bool,
+        class: Option<String>,
     ) -> Self {
         Self {
             declaration: Rc::new(RefCell::new(declaration.clone())),
+            globals: Environment::root(&closure),
             closure,
             is_initializer,
             is_getter: match declaration.kind {
                 FunType::Getter => true,
                 _ => false,
             },
+            class,
         }
     }
 
@@ -52,9 +56,56 @@ impl LoxFunction {
             closure: Rc::new(RefCell::new(environment)),
             is_initializer: self.is_initializer,
             is_getter: self.is_getter,
+            class: self.class.clone(),
+            globals: self.globals.clone(),
         };
     }
 
+    /// # is_same
+    /// Two functions are the same when they come from one declaration and close over one environment. Each `bind` makes a new closure, so every access to a method gives a different function.
+    pub fn is_same(&self, other: &LoxFunction) -> bool {
+        Rc::ptr_eq(&self.declaration, &other.declaration)
+            && Rc::ptr_eq(&self.closure, &other.closure)
+    }
+
+    /// # call_frame
+    /// The stack trace entry for a call to this function from the given line.
+    pub fn call_frame(&self, line: usize) -> CallFrame {
+        let declaration = self.declaration.borrow();
+
+        CallFrame {
+            function: match declaration.kind {
+                FunType::Lambda => "lambda".to_string(),
+                _ => declaration.name.lexeme.clone(),
+            },
+            class: self.class.clone(),
+            line,
+        }
+    }
+
+    /// # execute
+    /// Runs the body with the arguments bound to the parameters, without pushing a call frame.
+    pub fn execute(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxError> {
+        let mut environment = environment::Environment::with_enclosing(self.closure.clone());
+
+        let declaration = self.declaration.borrow();
+
+        // callers check the arity first, so every parameter gets an argument
+        debug_assert_eq!(declaration.params.len(), arguments.len());
+        for (param, argument) in declaration.params.iter().zip(arguments) {
+            environment.define(param.lexeme.clone(), argument);
+        }
+
+        // the body runs in place. A recursive call only borrows the declaration again, so it is never cloned
+        interpreter.in_module(self.globals.clone(), |interpreter| {
+            self.handle_block_stmt(interpreter, &declaration.body, environment)
+        })
+    }
+
     fn is_initializer(&self) -> bool {
         self.declaration.borrow().name.lexeme == "init"
     }
@@ -75,45 +126,33 @@ impl LoxFunction {
         self.closure.clone().to_owned()
     }
 
-    fn return_val_if_initializer(
-        &self,
-        return_value: Result<Object, LoxError>,
-    ) -> Result<Object, LoxError> {
+    fn return_val_if_initializer(&self, return_value: Object) -> Result<Object, LoxError> {
         if self.is_initializer() {
             return match self
                 .closure
                 .clone()
                 .borrow()
-                .get_at(0, &create_this_token(None, None))
+                .get_at(0, 0, &create_this_token(None))
             {
                 Ok(this) => Ok(this.clone()),
                 Err(e) => Err(LoxError::RuntimeError(e)),
             };
         } else {
-            return return_value;
+            return Ok(return_value);
         }
     }
 
     fn handle_block_stmt(
         &self,
         interpreter: &mut Interpreter,
-        declaration_body: Vec<Stmt>,
+        declaration_body: &[Stmt],
         environment: Environment,
     ) -> Result<Object, LoxError> {
-        return match interpreter.execute_block_stmt(
-            &mut BlockStmt {
-                statements: declaration_body,
-            },
-            environment,
-        ) {
-            Ok(value) => self.return_val_if_initializer(Ok(value)),
-            Err(e) => match e {
-                LoxError::RuntimeError(e) => Err(LoxError::RuntimeError(e)),
-                LoxError::LoxReturn(return_value) => {
-                    self.return_val_if_initializer(Err(LoxError::LoxReturn(return_value)))
-                }
-                LoxError::ParseError(e) => Err(LoxError::ParseError(e)),
-            },
+        return match interpreter.execute_block_stmt(declaration_body, environment) {
+            Ok(Completion::Return(value)) => self.return_val_if_initializer(*value),
+            // the resolver rejects break and continue outside a loop, so only a return can end the body early
+            Ok(_) => self.return_val_if_initializer(Object::Nil),
+            Err(e) => Err(e),
         };
     }
 }
@@ -128,18 +167,8 @@ impl LoxCallable<Result<Object, LoxError>> for LoxFunction {
         interpreter: &mut Interpreter,
         arguments: Vec<Object>,
     ) -> Result<Object, LoxError> {
-        let mut environment = environment::Environment::with_enclosing(self.closure.clone());
-
-        let dec_clone_one = self.declaration.clone();
-
-        for (i, param) in dec_clone_one.borrow().params.iter().enumerate() {
-            environment.define(param.lexeme.clone(), arguments[i].clone());
-        }
-
-        let dec_clone_two = self.declaration.clone();
-
-        let declaration_body = dec_clone_two.borrow_mut().body.clone();
+        let frame = self.call_frame(interpreter.call_site_line());
 
-        self.handle_block_stmt(interpreter, declaration_body, environment)
+        interpreter.in_frame(frame, |interpreter| self.execute(interpreter, arguments))
     }
 }
@@ -1,6 +1,11 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    any::Any,
+    cell::{RefCell, RefMut},
+    collections::HashMap,
+    rc::Rc,
+};
 
-use crate::{environment::generate_id, error::runtime_error::RuntimeError};
+use crate::{environment::generate_object_id, error::runtime_error::RuntimeError};
 
 use super::{
     callable::Callable, class::LoxClass, function::LoxFunction, object::Object, token::Token,
@@ -9,10 +14,13 @@ use super::{
 #[derive(Debug, Clone)]
 /// ## LoxInstance
 /// Lox instances store state for lox classes
+///
+/// Instances of classes defined from Rust can also hold a Rust value, the payload, which their native methods read and change. Copies of an instance share their fields and their payload.
 pub struct LoxInstance {
     class: Rc<RefCell<LoxClass>>,
     pub fields: Rc<RefCell<HashMap<String, Object>>>,
-    _id: String,
+    payload: Rc<RefCell<Option<Box<dyn Any>>>>,
+    id: usize,
 }
 
 impl LoxInstance {
@@ -20,16 +28,46 @@ impl LoxInstance {
         Self {
             class,
             fields: Rc::new(RefCell::new(HashMap::new())),
-            _id: generate_id(),
+            payload: Rc::new(RefCell::new(None)),
+            id: generate_object_id(),
         }
     }
 
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// # set_payload
+    /// Stores the Rust value of the instance, replacing any value it held.
+    pub fn set_payload<T: Any>(&self, payload: T) {
+        *self.payload.borrow_mut() = Some(Box::new(payload));
+    }
+
+    /// # payload
+    /// The Rust value of the instance, if it holds one of type T. It stays borrowed until the result is dropped.
+    pub fn payload<T: Any>(&self) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.payload.borrow_mut(), |payload| {
+            payload
+                .as_mut()
+                .and_then(|payload| payload.downcast_mut::<T>())
+        })
+        .ok()
+    }
+
     pub fn get(&self, name: &Token) -> Result<Object, RuntimeError> {
         // if it is a field
         if let Some(value) = self.fields.clone().borrow().get(name.lexeme.as_str()) {
             return Ok(value.clone());
         }
 
+        // if it is a native method, bound to this instance
+        let native_method = self.class.borrow().find_native_method(&name.lexeme);
+        if let Some(method) = native_method {
+            return Ok(Object::Callable(Callable::LoxNativeFunction(
+                method.bind(self.clone()),
+            )));
+        }
+
         // if it is a method
         match self.find_method(name) {
             Some(method) => Ok(Object::Callable(Callable::LoxFunction(method))),
@@ -54,6 +92,16 @@ impl LoxInstance {
         None
     }
 
+    /// # find_setter
+    /// Returns the class's setter for the property, bound to this instance.
+    pub fn find_setter(&self, name: &Token) -> Option<LoxFunction> {
+        let class = self.class.borrow();
+
+        class
+            .find_setter(&name.lexeme)
+            .map(|mut setter| setter.bind(self.clone()))
+    }
+
     pub fn set(&mut self, name: &Token, value: Object) -> Option<Object> {
         let value = self
             .fields
@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
+use super::object::Object;
+
+#[derive(Debug, Clone)]
+/// ## MapKey
+/// The hashable subset of Lox values. Only strings, numbers and booleans can be used as map keys.
+///
+/// f64 has no Hash, so numbers are stored by their bit pattern. Before hashing, -0 is folded into 0 so that the two compare equal like they do in Lox. NaN is never equal to itself, so it is rejected as a key.
+pub enum MapKey {
+    Str(String),
+    Num(u64),
+    Bool(bool),
+}
+
+impl MapKey {
+    pub fn from_object(object: &Object) -> Result<MapKey, String> {
+        match object {
+            Object::Str(string) => Ok(MapKey::Str(string.clone())),
+            Object::Num(number) if number.is_nan() => {
+                Err("NaN cannot be used as a map key.".to_string())
+            }
+            Object::Num(number) => {
+                let number = if *number == 0.0 { 0.0 } else { *number };
+                Ok(MapKey::Num(number.to_bits()))
+            }
+            Object::Bool(boolean) => Ok(MapKey::Bool(*boolean)),
+            _ => Err(format!(
+                "Unhashable map key of type {}. Map keys must be strings, numbers or booleans.",
+                object.type_name()
+            )),
+        }
+    }
+
+    pub fn to_object(&self) -> Object {
+        match self {
+            MapKey::Str(string) => Object::Str(string.clone()),
+            MapKey::Num(bits) => Object::Num(f64::from_bits(*bits)),
+            MapKey::Bool(boolean) => Object::Bool(*boolean),
+        }
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (MapKey::Str(a), MapKey::Str(b)) => a == b,
+            (MapKey::Num(a), MapKey::Num(b)) => a == b,
+            (MapKey::Bool(a), MapKey::Bool(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for MapKey {}
+
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            MapKey::Str(string) => {
+                state.write_u8(0); // Variant tag
+                string.hash(state)
+            }
+            MapKey::Num(bits) => {
+                state.write_u8(1);
+                bits.hash(state)
+            }
+            MapKey::Bool(boolean) => {
+                state.write_u8(2);
+                boolean.hash(state)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// ## LoxMap
+/// The backing store for Lox maps. Entries are kept in insertion order so that printing a map and calling keys() or values() are deterministic.
+pub struct LoxMap {
+    entries: Vec<(MapKey, Object)>,
+    positions: HashMap<MapKey, usize>,
+}
+
+impl LoxMap {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&Object> {
+        self.positions
+            .get(key)
+            .map(|position| &self.entries[*position].1)
+    }
+
+    pub fn contains_key(&self, key: &MapKey) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    /// # insert
+    /// Inserts or overwrites the value for a key. Overwriting keeps the key's original position.
+    pub fn insert(&mut self, key: MapKey, value: Object) {
+        match self.positions.get(&key) {
+            Some(position) => self.entries[*position].1 = value,
+            None => {
+                self.positions.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<Object> {
+        let position = self.positions.remove(key)?;
+        let (_, value) = self.entries.remove(position);
+
+        // every entry after the removed one has shifted down by one
+        for later_position in self.positions.values_mut() {
+            if *later_position > position {
+                *later_position -= 1;
+            }
+        }
+
+        Some(value)
+    }
+
+    pub fn keys(&self) -> Vec<Object> {
+        self.entries
+            .iter()
+            .map(|(key, _)| key.to_object())
+            .collect()
+    }
+
+    pub fn values(&self) -> Vec<Object> {
+        self.entries
+            .iter()
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_zero_is_the_same_key_as_zero() {
+        let zero = MapKey::from_object(&Object::Num(0.0)).unwrap();
+        let negative_zero = MapKey::from_object(&Object::Num(-0.0)).unwrap();
+
+        assert_eq!(zero, negative_zero);
+    }
+
+    #[test]
+    fn test_nan_and_nil_are_unhashable() {
+        assert!(MapKey::from_object(&Object::Num(f64::NAN)).is_err());
+        assert!(MapKey::from_object(&Object::Nil).is_err());
+    }
+
+    #[test]
+    fn test_remove_keeps_insertion_order() {
+        let mut map = LoxMap::new();
+        for key in ["a", "b", "c"] {
+            map.insert(MapKey::Str(key.to_string()), Object::Num(1.0));
+        }
+
+        map.remove(&MapKey::Str("a".to_string()));
+        map.insert(MapKey::Str("c".to_string()), Object::Num(3.0));
+
+        let keys: Vec<String> = map.keys().iter().map(|key| key.to_string()).collect();
+        assert_eq!(keys, vec!["b", "c"]);
+        match map.get(&MapKey::Str("c".to_string())) {
+            Some(Object::Num(n)) => assert_eq!(*n, 3.0),
+            _ => panic!("Expected the overwritten value"),
+        }
+    }
+}
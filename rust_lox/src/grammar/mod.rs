@@ -1,8 +1,11 @@
 pub mod callable;
 pub mod class;
+pub mod completion;
+pub mod convert;
 pub mod expr;
 pub mod function;
 pub mod instance;
+pub mod map;
 pub mod native_function;
 pub mod object;
 pub mod stmt;
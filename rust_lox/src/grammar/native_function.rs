@@ -1,63 +1,316 @@
-use crate::{error::LoxError, interpreter::Interpreter};
+use std::{cell::RefCell, fmt, rc::Rc};
 
-use super::{callable::LoxCallable, object::Object};
+use crate::interpreter::Interpreter;
 
-#[derive(Debug, Clone)]
-pub enum LoxNativeFunctions {
-    Clock(Clock),
+use super::{
+    callable::LoxCallable,
+    convert::{FromLox, IntoLox},
+    instance::LoxInstance,
+    map::{LoxMap, MapKey},
+    object::Object,
+};
+
+/// Native functions report failures as plain messages. `Callable::call` turns them into runtime errors at the call site.
+pub type NativeResult = Result<Object, String>;
+
+/// The Rust side of a native function. It gets the interpreter and the arguments, already checked against the arity.
+pub type NativeFn = dyn Fn(&mut Interpreter, Vec<Object>) -> NativeResult;
+
+/// # LoxNativeFunction
+/// Crafting Interpreters 10.2 - "These are functions that the interpreter exposes to user code but that are implemented in the host language (in our case Java), not the language being implemented (Lox)."
+///
+/// The builtins are registered as closures, the same way a host registers its own with `Lox::define_native`. Copies share the closure, so a native is only equal to copies of itself.
+#[derive(Clone)]
+pub struct LoxNativeFunction {
+    name: String,
+    arity: u8,
+    function: Rc<NativeFn>,
 }
 
-#[allow(dead_code)]
-struct LoxNativeFunction {
-    function: LoxNativeFunctions,
+impl LoxNativeFunction {
+    pub fn new(
+        name: &str,
+        arity: u8,
+        function: impl Fn(&mut Interpreter, Vec<Object>) -> NativeResult + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            function: Rc::new(function),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_same(&self, other: &LoxNativeFunction) -> bool {
+        Rc::ptr_eq(&self.function, &other.function)
+    }
 }
 
-impl LoxCallable<Result<Object, LoxError>> for LoxNativeFunction {
+impl LoxCallable<NativeResult> for LoxNativeFunction {
     fn arity(&self) -> u8 {
-        match self.function {
-            LoxNativeFunctions::Clock(Clock) => 0,
-        }
+        self.arity
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, arguments: Vec<Object>) -> NativeResult {
+        (self.function)(interpreter, arguments)
+    }
+}
+
+impl fmt::Display for LoxNativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
     }
+}
+
+impl fmt::Debug for LoxNativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
 
-    fn call(
-        &self,
-        interpreter: &mut Interpreter,
-        _arguments: Vec<Object>,
-    ) -> Result<Object, LoxError> {
-        match self.function {
-            LoxNativeFunctions::Clock(Clock) => Clock::new().call(interpreter, vec![]),
+/// The Rust side of a native method. It also gets the instance the method was accessed on, which is `this`.
+pub type NativeMethodFn = dyn Fn(&mut Interpreter, &LoxInstance, Vec<Object>) -> NativeResult;
+
+/// # NativeMethod
+/// A method of a class defined from Rust. A native `init` runs when the class is called, and usually stores the instance's Rust state with `LoxInstance::set_payload`.
+///
+/// Accessing the method on an instance binds it, giving a native function with `this` captured.
+#[derive(Clone)]
+pub struct NativeMethod {
+    name: String,
+    arity: u8,
+    function: Rc<NativeMethodFn>,
+}
+
+impl NativeMethod {
+    pub fn new(
+        name: &str,
+        arity: u8,
+        function: impl Fn(&mut Interpreter, &LoxInstance, Vec<Object>) -> NativeResult + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            function: Rc::new(function),
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> u8 {
+        self.arity
+    }
+
+    pub fn bind(&self, instance: LoxInstance) -> LoxNativeFunction {
+        let function = Rc::clone(&self.function);
+
+        LoxNativeFunction::new(&self.name, self.arity, move |interpreter, arguments| {
+            function(interpreter, &instance, arguments)
+        })
+    }
 }
 
-/// # Clock - LoxNativeFunction
-/// Crafting Interpreters 10.2 - "These are functions that the interpreter exposes to user code but that are implemented in the host language (in our case Java), not the language being implemented (Lox)."
-#[derive(Debug, Clone)]
-pub struct Clock;
-impl Clock {
-    pub fn new() -> Self {
-        Self {}
+impl fmt::Debug for NativeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native method {}>", self.name)
     }
+}
 
-    pub fn _string(&self) -> String {
-        String::from("<native fn>")
+/// # IntoNativeResult
+/// What a typed native function can return: a value that converts into Lox, or a `Result` whose error message becomes a runtime error.
+pub trait IntoNativeResult {
+    fn into_native_result(self) -> NativeResult;
+}
+
+impl<T: IntoLox> IntoNativeResult for T {
+    fn into_native_result(self) -> NativeResult {
+        Ok(self.into_lox())
     }
 }
-impl LoxCallable<Result<Object, LoxError>> for Clock {
-    fn arity(&self) -> u8 {
-        0
+
+impl<T: IntoLox> IntoNativeResult for Result<T, String> {
+    fn into_native_result(self) -> NativeResult {
+        self.map(IntoLox::into_lox)
+    }
+}
+
+/// # TypedNative
+/// A Rust function that takes arguments of types implementing `FromLox`, like `|a: f64, b: f64| a + b`. Its arity is its number of parameters, and each argument is converted before the call.
+pub trait TypedNative<Arguments> {
+    fn into_native(self, name: &str) -> LoxNativeFunction;
+}
+
+/// Converts the argument at the position, naming the function and the position if it has the wrong type.
+fn typed_argument<T: FromLox>(
+    name: &str,
+    position: usize,
+    arguments: &[Object],
+) -> Result<T, String> {
+    let argument = arguments.get(position).unwrap_or(&Object::Nil);
+
+    T::from_lox(argument)
+        .map_err(|error| format!("Bad argument {} to {}(). {}", position + 1, name, error))
+}
+
+macro_rules! typed_native {
+    ($arity:literal; $($argument:ident),*) => {
+        impl<F, R, $($argument: FromLox),*> TypedNative<($($argument,)*)> for F
+        where
+            F: Fn($($argument),*) -> R + 'static,
+            R: IntoNativeResult,
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_native(self, name: &str) -> LoxNativeFunction {
+                let native_name = name.to_string();
+
+                LoxNativeFunction::new(name, $arity, move |_interpreter, arguments| {
+                    let mut positions = 0..;
+                    $(
+                        let $argument = typed_argument::<$argument>(
+                            &native_name,
+                            positions.next().unwrap_or_default(),
+                            &arguments,
+                        )?;
+                    )*
+
+                    self($($argument),*).into_native_result()
+                })
+            }
+        }
+    };
+}
+
+typed_native!(0;);
+typed_native!(1; A);
+typed_native!(2; A, B);
+typed_native!(3; A, B, C);
+typed_native!(4; A, B, C, D);
+typed_native!(5; A, B, C, D, E);
+
+/// # builtins
+/// The native functions every global environment starts with.
+pub fn builtins() -> Vec<LoxNativeFunction> {
+    vec![
+        LoxNativeFunction::new("clock", 0, clock),
+        LoxNativeFunction::new("len", 1, len),
+        LoxNativeFunction::new("push", 2, push),
+        LoxNativeFunction::new("pop", 1, pop),
+        LoxNativeFunction::new("keys", 1, keys),
+        LoxNativeFunction::new("values", 1, values),
+        LoxNativeFunction::new("has", 2, has),
+        LoxNativeFunction::new("remove", 2, remove),
+    ]
+}
+
+/// Returns the list passed as the first argument of a list native.
+fn list_argument(name: &str, arguments: &[Object]) -> Result<Rc<RefCell<Vec<Object>>>, String> {
+    match arguments.first() {
+        Some(Object::List(list)) => Ok(Rc::clone(list)),
+        _ => Err(format!("{}() expects a list as its first argument.", name)),
+    }
+}
+
+/// Returns the map passed as the first argument of a map native.
+fn map_argument(name: &str, arguments: &[Object]) -> Result<Rc<RefCell<LoxMap>>, String> {
+    match arguments.first() {
+        Some(Object::Map(map)) => Ok(Rc::clone(map)),
+        _ => Err(format!("{}() expects a map as its first argument.", name)),
     }
+}
+
+/// Returns the key passed as the second argument of a map native.
+fn key_argument(name: &str, arguments: &[Object]) -> Result<MapKey, String> {
+    match arguments.get(1) {
+        Some(key) => MapKey::from_object(key),
+        None => Err(format!("{}() expects a key as its second argument.", name)),
+    }
+}
+
+/// # clock
+/// `clock()` returns the number of seconds since the Unix epoch.
+fn clock(_interpreter: &mut Interpreter, _arguments: Vec<Object>) -> NativeResult {
+    Ok(Object::Num(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64(),
+    ))
+}
 
-    fn call(
-        &self,
-        _interpreter: &mut Interpreter,
-        _arguments: Vec<Object>,
-    ) -> Result<Object, LoxError> {
-        Ok(Object::Num(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs_f64(),
-        ))
+/// # len
+/// `len(value)` returns the number of elements in a list, entries in a map or characters in a string.
+fn len(_interpreter: &mut Interpreter, arguments: Vec<Object>) -> NativeResult {
+    match arguments.first() {
+        Some(Object::List(list)) => Ok(Object::Num(list.borrow().len() as f64)),
+        Some(Object::Map(map)) => Ok(Object::Num(map.borrow().len() as f64)),
+        Some(Object::Str(string)) => Ok(Object::Num(string.chars().count() as f64)),
+        _ => Err("len() expects a list, a map or a string.".to_string()),
     }
 }
+
+/// # push
+/// `push(list, value)` appends the value to the end of the list and returns the list's new length.
+fn push(_interpreter: &mut Interpreter, arguments: Vec<Object>) -> NativeResult {
+    let list = list_argument("push", &arguments)?;
+    let value = arguments
+        .get(1)
+        .cloned()
+        .ok_or_else(|| "push() expects a value to append.".to_string())?;
+
+    list.borrow_mut().push(value);
+
+    let length = list.borrow().len();
+    Ok(Object::Num(length as f64))
+}
+
+/// # pop
+/// `pop(list)` removes the last element of the list and returns it.
+fn pop(_interpreter: &mut Interpreter, arguments: Vec<Object>) -> NativeResult {
+    let list = list_argument("pop", &arguments)?;
+    let value = list.borrow_mut().pop();
+
+    value.ok_or_else(|| "Cannot pop from an empty list.".to_string())
+}
+
+/// # keys
+/// `keys(map)` returns a list of the map's keys in insertion order.
+fn keys(_interpreter: &mut Interpreter, arguments: Vec<Object>) -> NativeResult {
+    let map = map_argument("keys", &arguments)?;
+    let keys = map.borrow().keys();
+
+    Ok(Object::List(Rc::new(RefCell::new(keys))))
+}
+
+/// # values
+/// `values(map)` returns a list of the map's values in insertion order.
+fn values(_interpreter: &mut Interpreter, arguments: Vec<Object>) -> NativeResult {
+    let map = map_argument("values", &arguments)?;
+    let values = map.borrow().values();
+
+    Ok(Object::List(Rc::new(RefCell::new(values))))
+}
+
+/// # has
+/// `has(map, key)` returns whether the map contains the key.
+fn has(_interpreter: &mut Interpreter, arguments: Vec<Object>) -> NativeResult {
+    let map = map_argument("has", &arguments)?;
+    let key = key_argument("has", &arguments)?;
+    let contains_key = map.borrow().contains_key(&key);
+
+    Ok(Object::Bool(contains_key))
+}
+
+/// # remove
+/// `remove(map, key)` removes the key from the map and returns its value, or nil if the key was not there.
+fn remove(_interpreter: &mut Interpreter, arguments: Vec<Object>) -> NativeResult {
+    let map = map_argument("remove", &arguments)?;
+    let key = key_argument("remove", &arguments)?;
+    let value = map.borrow_mut().remove(&key);
+
+    Ok(value.unwrap_or(Object::Nil))
+}
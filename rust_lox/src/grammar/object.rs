@@ -1,6 +1,6 @@
-use std::fmt;
+use std::{cell::RefCell, fmt, rc::Rc};
 
-use super::{callable::Callable, instance::LoxInstance};
+use super::{callable::Callable, instance::LoxInstance, map::LoxMap};
 
 pub enum Object {
     Str(String),
@@ -8,7 +8,10 @@ pub enum Object {
     Bool(bool),
     Callable(Callable),
     Instance(LoxInstance),
-    // Return(Box<Object>), // TODO: Implement Return and remove the return error type. The LoxReturn error type is not idiomatic Rust
+    /// Lists are shared and mutable. Every copy of a list value refers to the same elements.
+    List(Rc<RefCell<Vec<Object>>>),
+    /// Maps are shared and mutable in the same way as lists.
+    Map(Rc<RefCell<LoxMap>>),
     Nil,
 }
 
@@ -27,6 +30,40 @@ impl Object {
         }
     }
 
+    /// # is_equal
+    /// Lox equality. Values of different types are never equal, and nil is only equal to nil.
+    ///
+    /// Strings, numbers and booleans compare by value. Numbers follow IEEE 754, so NaN is not equal to itself. Instances, classes, functions, lists and maps compare by identity.
+    pub fn is_equal(&self, other: &Object) -> bool {
+        match (self, other) {
+            (Object::Num(a), Object::Num(b)) => a == b,
+            (Object::Str(a), Object::Str(b)) => a == b,
+            (Object::Bool(a), Object::Bool(b)) => a == b,
+            (Object::Nil, Object::Nil) => true,
+            (Object::Instance(a), Object::Instance(b)) => a.id() == b.id(),
+            (Object::Callable(a), Object::Callable(b)) => a.is_same(b),
+            (Object::List(a), Object::List(b)) => Rc::ptr_eq(a, b),
+            (Object::Map(a), Object::Map(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// # type_name
+    /// returns the name of the value's type as it should read in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Str(_) => "string",
+            Object::Num(_) => "number",
+            Object::Bool(_) => "boolean",
+            Object::Callable(Callable::LoxClass(_)) => "class",
+            Object::Callable(_) => "function",
+            Object::Instance(_) => "instance",
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::Nil => "nil",
+        }
+    }
+
     pub fn _print(&self) {
         match self {
             Object::Str(string) => println!("{string}"),
@@ -34,6 +71,8 @@ impl Object {
             Object::Num(number) => println!("{number}"),
             Object::Callable(_) => println!("<LoxCallable>"),
             Object::Instance(_) => println!("<LoxInstance>"),
+            Object::List(_) => println!("{}", self),
+            Object::Map(_) => println!("{}", self),
             Object::Nil => println!("<LoxNil>"),
         }
     }
@@ -45,11 +84,106 @@ impl Object {
             Object::Num(number) => format!("{number}"),
             Object::Callable(_) => format!("<LoxCallable>"),
             Object::Instance(_) => format!("<LoxInstance>"),
+            Object::List(_) => format!("{}", self),
+            Object::Map(_) => format!("{}", self),
             Object::Nil => String::from("<LoxNil>"),
         }
     }
 }
 
+/// The addresses of the lists and maps being printed, outermost first. A list or map that holds itself prints as `[...]` or `{...}` where it repeats, instead of recursing until the stack overflows.
+type Printing = Vec<*const ()>;
+
+impl Object {
+    fn fmt_display(&self, f: &mut fmt::Formatter, printing: &mut Printing) -> fmt::Result {
+        match self {
+            Object::List(list) => {
+                let address = Rc::as_ptr(list) as *const ();
+                if printing.contains(&address) {
+                    return write!(f, "[...]");
+                }
+
+                printing.push(address);
+                write!(f, "[")?;
+                for (index, element) in list.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_display(f, printing)?;
+                }
+                printing.pop();
+
+                write!(f, "]")
+            }
+            Object::Map(map) => {
+                let address = Rc::as_ptr(map) as *const ();
+                if printing.contains(&address) {
+                    return write!(f, "{{...}}");
+                }
+
+                printing.push(address);
+                write!(f, "{{")?;
+                let map = map.borrow();
+                for (index, (key, value)) in map.keys().iter().zip(map.values()).enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", key)?;
+                    value.fmt_display(f, printing)?;
+                }
+                printing.pop();
+
+                write!(f, "}}")
+            }
+            _ => write!(f, "{}", self),
+        }
+    }
+
+    fn fmt_debug(&self, f: &mut fmt::Formatter, printing: &mut Printing) -> fmt::Result {
+        match self {
+            Object::List(list) => {
+                let address = Rc::as_ptr(list) as *const ();
+                if printing.contains(&address) {
+                    return write!(f, "List([...])");
+                }
+
+                printing.push(address);
+                write!(f, "List([")?;
+                for (index, element) in list.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_debug(f, printing)?;
+                }
+                printing.pop();
+
+                write!(f, "])")
+            }
+            Object::Map(map) => {
+                let address = Rc::as_ptr(map) as *const ();
+                if printing.contains(&address) {
+                    return write!(f, "Map({{...}})");
+                }
+
+                printing.push(address);
+                write!(f, "Map({{")?;
+                let map = map.borrow();
+                for (index, (key, value)) in map.keys().iter().zip(map.values()).enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: ", key)?;
+                    value.fmt_debug(f, printing)?;
+                }
+                printing.pop();
+
+                write!(f, "}})")
+            }
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -57,6 +191,8 @@ impl fmt::Display for Object {
             Object::Bool(boolean) => write!(f, "{}", boolean),
             Object::Num(number) => write!(f, "{}", number),
             Object::Instance(instance) => write!(f, "{}", instance.to_string()),
+            Object::List(_) => self.fmt_display(f, &mut Vec::new()),
+            Object::Map(_) => self.fmt_display(f, &mut Vec::new()),
             Object::Callable(callable) => match callable {
                 Callable::LoxFunction(func) => {
                     write!(f, "Object: {}", func._to_string())
@@ -64,9 +200,7 @@ impl fmt::Display for Object {
                 Callable::LoxClass(c) => {
                     write!(f, "{}", c.name())
                 }
-                Callable::LoxNativeFunction(func) => match func {
-                    _ => write!(f, "Object: <LoxNativeFunction>"),
-                },
+                Callable::LoxNativeFunction(native) => write!(f, "{}", native),
             },
             Object::Nil => write!(f, "<LoxNil>"),
         }
@@ -86,11 +220,11 @@ impl fmt::Debug for Object {
                 Callable::LoxClass(c) => {
                     write!(f, "{}", c.name())
                 }
-                Callable::LoxNativeFunction(func) => match func {
-                    _ => write!(f, "Object: <LoxNativeFunction>"),
-                },
+                Callable::LoxNativeFunction(native) => write!(f, "{}", native),
             },
             Object::Instance(i) => write!(f, "Instance({:?})", i),
+            Object::List(_) => self.fmt_debug(f, &mut Vec::new()),
+            Object::Map(_) => self.fmt_debug(f, &mut Vec::new()),
             Object::Nil => write!(f, "<LoxNil>"),
         }
     }
@@ -104,7 +238,60 @@ impl Clone for Object {
             Object::Bool(b) => Object::Bool(*b),
             Object::Callable(callable) => Object::Callable(callable.clone()), // Choose to return Nil for Callable
             Object::Instance(i) => Object::Instance(i.clone()),
+            Object::List(list) => Object::List(Rc::clone(list)),
+            Object::Map(map) => Object::Map(Rc::clone(map)),
             Object::Nil => Object::Nil,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::map::MapKey;
+
+    #[test]
+    fn test_list_that_holds_itself_prints() {
+        let list = Rc::new(RefCell::new(vec![Object::Num(1.0)]));
+        list.borrow_mut().push(Object::List(Rc::clone(&list)));
+        let outer = Object::List(Rc::new(RefCell::new(vec![
+            Object::List(Rc::clone(&list)),
+            Object::List(Rc::clone(&list)),
+        ])));
+
+        assert_eq!(Object::List(Rc::clone(&list)).to_string(), "[1, [...]]");
+        assert_eq!(outer.to_string(), "[[1, [...]], [1, [...]]]");
+        assert_eq!(
+            format!("{:?}", Object::List(Rc::clone(&list))),
+            "List([Num(1), List([...])])"
+        );
+
+        // break the cycle so the list is freed
+        list.borrow_mut().clear();
+    }
+
+    #[test]
+    fn test_map_that_holds_itself_prints() {
+        let map = Rc::new(RefCell::new(LoxMap::new()));
+        let list = Rc::new(RefCell::new(vec![Object::Map(Rc::clone(&map))]));
+        map.borrow_mut().insert(
+            MapKey::Str("self".to_string()),
+            Object::Map(Rc::clone(&map)),
+        );
+        map.borrow_mut()
+            .insert(MapKey::Num(1f64.to_bits()), Object::List(Rc::clone(&list)));
+
+        assert_eq!(
+            Object::Map(Rc::clone(&map)).to_string(),
+            "{self: {...}, 1: [{...}]}"
+        );
+        assert_eq!(
+            format!("{:?}", Object::Map(Rc::clone(&map))),
+            "Map({Str(\"self\"): Map({...}), Num(1): List([Map({...})])})"
+        );
+
+        // break the cycles so the map and list are freed
+        list.borrow_mut().clear();
+        map.borrow_mut().remove(&MapKey::Str("self".to_string()));
+    }
+}
@@ -1,5 +1,6 @@
 use super::{
     expr::{Expr, Variable},
+    object::Object,
     token::Token,
 };
 
@@ -12,6 +13,10 @@ pub struct BlockStmt {
 pub enum FunType {
     Function,
     Getter,
+    /// Declared with `set name(value) { ... }`. Assigning to the property calls it instead of writing a field.
+    Setter,
+    /// Anonymous function expression. The name token is the `fun` keyword.
+    Lambda,
 }
 
 #[derive(Debug, Clone)]
@@ -29,6 +34,48 @@ pub struct ClassStmt {
     /// "The grammar restricts the superclass clause to a single identifier, but at runtime, that identifier is evaluated as a variable access. Wrapping the name in an Expr.Variable early on in the parser gives us an object that the resolver can hang the resolution information off of"
     pub superclass: Option<Variable>,
     pub methods: Vec<FunStmt>,
+    /// ## class_methods
+    /// Static methods, declared with a `class` prefix inside the class body. They are called on the class itself and have no `this`.
+    pub class_methods: Vec<FunStmt>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CatchClause {
+    /// The variable that holds the caught value inside the catch block.
+    pub name: Token,
+    pub body: BlockStmt,
+}
+
+#[derive(Debug, Clone)]
+/// ## TryStmt
+/// `try { } catch (e) { } finally { }`. At least one of the catch and finally clauses is present.
+pub struct TryStmt {
+    pub body: BlockStmt,
+    pub catch: Option<CatchClause>,
+    pub finally: Option<BlockStmt>,
+}
+
+#[derive(Debug, Clone)]
+/// ## ImportStmt
+/// `import "path.lox";`, `import "path.lox" as name;` or `import "path.lox" for a, b;`.
+pub struct ImportStmt {
+    pub keyword: Token,
+    /// The string literal token holding the module path, relative to the importing file.
+    pub path: Token,
+    /// Bound to a namespace object holding every export of the module.
+    pub alias: Option<Token>,
+    /// Exports bound directly into the importing scope.
+    pub names: Vec<Token>,
+}
+
+impl ImportStmt {
+    /// The decoded module path, as written between the quotes.
+    pub fn module_name(&self) -> String {
+        match &self.path.literal {
+            Some(Object::Str(path)) => path.clone(),
+            _ => self.path.lexeme.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -36,7 +83,14 @@ pub struct ClassStmt {
 /// Statements form a second hierarchy of syntax tree nodes independent of expressions. We add the first couple of them in “Statements and State”.
 pub enum Stmt {
     Block(BlockStmt),
-    Class(ClassStmt),
+    Break {
+        keyword: Token,
+    },
+    /// Classes and imports are boxed. They are rare and much larger than the other statements.
+    Class(Box<ClassStmt>),
+    Continue {
+        keyword: Token,
+    },
     Expression {
         expression: Box<Expr>,
     },
@@ -47,12 +101,19 @@ pub enum Stmt {
         else_branch: Option<Box<Stmt>>,
     },
     Print {
+        keyword: Token,
         expression: Box<Expr>,
     },
     Return {
         keyword: Token,
         value: Box<Expr>,
     },
+    Throw {
+        keyword: Token,
+        value: Box<Expr>,
+    },
+    Try(TryStmt),
+    Import(Box<ImportStmt>),
     Var {
         name: Token,
         initializer: Box<Expr>,
@@ -61,11 +122,14 @@ pub enum Stmt {
     While {
         condition: Box<Expr>,
         body: Box<Stmt>,
+        /// ## increment
+        /// The increment clause of a desugared for loop. It is kept out of the body so that `continue` still runs it.
+        increment: Option<Box<Expr>>,
     },
 }
 
 impl Stmt {
-    pub fn accept<R>(&mut self, visitor: &mut impl StmtVisitor<R>) -> R {
+    pub fn accept<R>(&self, visitor: &mut impl StmtVisitor<R>) -> R {
         match self {
             Stmt::Expression { expression } => visitor.visit_expression_stmt(expression),
             Stmt::If {
@@ -73,9 +137,21 @@ impl Stmt {
                 then_branch,
                 else_branch,
             } => visitor.visit_if_stmt(condition, then_branch, else_branch),
-            Stmt::While { condition, body } => visitor.visit_while_stmt(condition, body),
-            Stmt::Print { expression } => visitor.visit_print_stmt(expression),
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => visitor.visit_while_stmt(condition, body, increment),
+            Stmt::Break { keyword } => visitor.visit_break_stmt(keyword),
+            Stmt::Continue { keyword } => visitor.visit_continue_stmt(keyword),
+            Stmt::Print {
+                keyword,
+                expression,
+            } => visitor.visit_print_stmt(keyword, expression),
             Stmt::Return { keyword, value } => visitor.visit_return_stmt(keyword, value),
+            Stmt::Throw { keyword, value } => visitor.visit_throw_stmt(keyword, value),
+            Stmt::Try(try_stmt) => visitor.visit_try_stmt(try_stmt),
+            Stmt::Import(import_stmt) => visitor.visit_import_stmt(import_stmt),
             Stmt::Var { name, initializer } => visitor.visit_var_stmt(name, initializer),
             Stmt::Block(block_stmt) => visitor.visit_block_stmt(block_stmt),
             Stmt::Function(fun_stmt) => visitor.visit_function_stmt(fun_stmt),
@@ -89,14 +165,24 @@ pub trait StmtVisitor<R> {
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
-        then_branch: &mut Stmt,
-        else_branch: &mut Option<Box<Stmt>>,
+        then_branch: &Stmt,
+        else_branch: &Option<Box<Stmt>>,
+    ) -> R;
+    fn visit_while_stmt(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
     ) -> R;
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &mut Stmt) -> R;
-    fn visit_print_stmt(&mut self, expression: &Expr) -> R;
+    fn visit_break_stmt(&mut self, keyword: &Token) -> R;
+    fn visit_continue_stmt(&mut self, keyword: &Token) -> R;
+    fn visit_print_stmt(&mut self, keyword: &Token, expression: &Expr) -> R;
     fn visit_return_stmt(&mut self, keyword: &Token, value: &Expr) -> R;
+    fn visit_throw_stmt(&mut self, keyword: &Token, value: &Expr) -> R;
+    fn visit_try_stmt(&mut self, try_stmt: &TryStmt) -> R;
+    fn visit_import_stmt(&mut self, import_stmt: &ImportStmt) -> R;
     fn visit_var_stmt(&mut self, name: &Token, initializer: &Expr) -> R;
-    fn visit_block_stmt(&mut self, statements: &mut BlockStmt) -> R;
-    fn visit_function_stmt(&mut self, fun_stmt: &mut FunStmt) -> R;
+    fn visit_block_stmt(&mut self, statements: &BlockStmt) -> R;
+    fn visit_function_stmt(&mut self, fun_stmt: &FunStmt) -> R;
     fn visit_class_stmt(&mut self, class_stmt: &ClassStmt) -> R;
 }
@@ -1,8 +1,10 @@
 #![allow(dead_code)]
 
-use std::hash::{Hash, Hasher};
-
-use crate::environment::generate_id;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
 
 use super::object::Object;
 
@@ -13,6 +15,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -34,36 +39,78 @@ pub enum TokenType {
     // Object.
     Identifier,
     String,
+    /// The part of a string before a `${`. The interpolated expression's tokens follow it.
+    Interpolation,
     Number,
 
     // Keywords.
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
     Eof,
 }
 
+#[derive(Clone, Default)]
+/// ## Span
+/// Where a token was found in its source, so that errors can point at it.
+///
+/// - offset
+///   - Byte offset of the token's first character
+/// - column
+///   - Column of the token's first character, counted in characters from 1
+/// - length
+///   - Length of the token in bytes
+/// - source
+///   - The whole source the token was scanned from. Tokens the interpreter makes up have no source, and their errors are printed without a snippet
+pub struct Span {
+    pub offset: usize,
+    pub column: usize,
+    pub length: usize,
+    pub source: Option<Rc<str>>,
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the source is left out, it would repeat the whole script for every token
+        f.debug_struct("Span")
+            .field("offset", &self.offset)
+            .field("column", &self.column)
+            .field("length", &self.length)
+            .finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Object>,
     pub line: usize,
-    pub _id: String,
+    /// ## id
+    /// Unique for every token the scanner produces, and kept when the syntax tree is cloned. The resolver records where a variable lives under the id of the token that names it.
+    pub id: usize,
+    pub span: Span,
 }
 
 impl Token {
@@ -72,14 +119,15 @@ impl Token {
         lexeme: String,
         literal: Option<Object>,
         line: usize,
-        _id: String,
+        id: usize,
     ) -> Self {
         Self {
             token_type,
             lexeme,
             literal,
             line,
-            _id: generate_id(),
+            id,
+            span: Span::default(),
         }
     }
 
@@ -93,7 +141,7 @@ impl PartialEq for Token {
         self.token_type == other.token_type
             && self.lexeme == other.lexeme
             && self.line == other.line
-            && self._id == other._id
+            && self.id == other.id
     }
 }
 
@@ -103,11 +151,15 @@ impl Hash for Token {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.lexeme.hash(state);
         self.line.hash(state);
-        self._id.hash(state);
+        self.id.hash(state);
     }
 }
 
-pub fn create_this_token(id: Option<String>, line: Option<usize>) -> Token {
+/// # SYNTHETIC_ID
+/// The id of tokens made up at runtime, such as the token an error from a host call is reported at. They never name a resolved variable, so they share one id instead of taking new ones.
+pub const SYNTHETIC_ID: usize = usize::MAX;
+
+pub fn create_this_token(line: Option<usize>) -> Token {
     Token {
         token_type: TokenType::This,
         lexeme: String::from("this"),
@@ -116,32 +168,15 @@ pub fn create_this_token(id: Option<String>, line: Option<usize>) -> Token {
             Some(n) => n,
             _ => 1,
         },
-        _id: match id {
-            Some(id) => id,
-            _ => generate_id(),
-        },
-    }
-}
-
-pub fn create_super_token(id: Option<String>, line: Option<usize>) -> Token {
-    Token {
-        token_type: TokenType::Super,
-        lexeme: String::from("super"),
-        literal: Option::None,
-        line: match line {
-            Some(n) => n,
-            _ => 1,
-        },
-        _id: match id {
-            Some(id) => id,
-            _ => generate_id(),
-        },
+        id: SYNTHETIC_ID,
+        span: Span::default(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::environment::generate_id;
 
     #[test]
     fn test_token_equality() {
@@ -150,7 +185,8 @@ mod tests {
             lexeme: String::from("test"),
             literal: Option::None,
             line: 1,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
 
         let token2 = token1.clone();
@@ -165,7 +201,8 @@ mod tests {
             lexeme: String::from("test"),
             literal: Option::None,
             line: 1,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
 
         let token2 = Token {
@@ -173,7 +210,8 @@ mod tests {
             lexeme: String::from("test"),
             literal: Option::None,
             line: 2,
-            _id: generate_id(),
+            id: generate_id(),
+            span: Span::default(),
         };
 
         assert_ne!(token1, token2);
@@ -1,117 +1,401 @@
-use crate::environment::{generate_id, Environment};
-use crate::error::lox_return::LoxReturn;
-use crate::error::runtime_error::RuntimeError;
+use crate::environment::Environment;
+use crate::error::error::ErrorReporter;
+use crate::error::limit_error::LimitError;
+use crate::error::runtime_error::{CallFrame, RuntimeError};
 use crate::error::LoxError;
 use crate::grammar::callable::{Callable, LoxCallable};
 use crate::grammar::class::LoxClass;
+use crate::grammar::completion::Completion;
 use crate::grammar::expr::{Expr, ExprVisitor};
 use crate::grammar::function::LoxFunction;
-use crate::grammar::native_function::{Clock, LoxNativeFunctions};
+use crate::grammar::instance::LoxInstance;
+use crate::grammar::map::{LoxMap, MapKey};
+use crate::grammar::native_function::{
+    self, LoxNativeFunction, NativeMethod, NativeResult, TypedNative,
+};
 use crate::grammar::object::Object;
-use crate::grammar::stmt::{BlockStmt, ClassStmt, FunStmt, Stmt, StmtVisitor};
-use crate::grammar::token::{create_super_token, create_this_token, Token, TokenType};
+use crate::grammar::stmt::{
+    BlockStmt, ClassStmt, FunStmt, FunType, ImportStmt, Stmt, StmtVisitor, TryStmt,
+};
+use crate::grammar::token::{Token, TokenType, SYNTHETIC_ID};
+use crate::parser::Parser;
+use crate::resolver::{Locals, Resolver};
+use crate::scanner::Scanner;
 use std::collections::HashMap;
-use std::{cell::RefCell, rc::Rc};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{
+    cell::{Ref, RefCell},
+    rc::Rc,
+};
+
+/// # script_message
+/// The message of an error as a script sees it. Many messages end with where in the interpreter they were raised, like `-- Interpreter: visit_get_expr()`, which means nothing to a script.
+fn script_message(message: &str) -> String {
+    match message.rsplit_once(" -- ") {
+        Some((text, origin)) if origin.trim_end_matches('.').ends_with("()") => {
+            if text.ends_with('.') {
+                text.to_string()
+            } else {
+                format!("{}.", text)
+            }
+        }
+        _ => message.to_string(),
+    }
+}
+
+/// The file name of a module path, used in error messages.
+fn file_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => path.to_string_lossy().to_string(),
+    }
+}
 
 /// # Interpreter
 pub struct Interpreter {
     environment: Rc<RefCell<Environment>>,
-    pub globals: Rc<RefCell<Environment>>,
-    /// Locals stores the distance of a variable from the current scope. A given expression is so many scopes away from the current scope
-    // TODO: convert locals to a vector. Look up values by index, which needs saved the scopes vector in Resolver
-    locals: Rc<RefCell<HashMap<Expr, usize>>>,
+    /// Locals stores the distance of a variable from the current scope and its slot in that scope, by the id of the variable's token. A given variable is so many scopes away from the current scope
+    locals: Rc<RefCell<Locals>>,
+    /// The file being run. Imports are resolved relative to its directory, or to the working directory in the REPL.
+    script_path: Option<PathBuf>,
+    /// Global environments of the modules that finished loading, keyed by canonical path. A module runs only once.
+    modules: HashMap<PathBuf, Rc<RefCell<Environment>>>,
+    /// Modules that are still running, innermost last. Importing one of them again is a cycle.
+    loading: Vec<PathBuf>,
+    /// The Lox functions that are running, innermost last. Runtime errors leaving a function take a copy as their backtrace.
+    call_stack: Vec<CallFrame>,
+    /// The line of the call being made. Set just before a call so the callee's frame can record where it was called from.
+    call_site_line: usize,
+    /// How deep Lox calls may nest before a call fails with a stack overflow error.
+    max_call_depth: usize,
+    /// How many steps a run may take, if it is limited. Every statement executed and every expression evaluated is a step.
+    fuel: Option<u64>,
+    /// How long a run may take, if it is limited.
+    timeout: Option<Duration>,
+    /// Steps taken so far in the current run.
+    steps: u64,
+    /// When the current run has to stop by.
+    deadline: Option<Instant>,
+    /// Set once the deadline has passed, so the steps after it fail without reading the clock.
+    timed_out: bool,
+    /// The builtins followed by the natives the host defined. Every module's globals start with all of them.
+    natives: Vec<LoxNativeFunction>,
+    /// The classes the host defined, which every module's globals start with as well.
+    native_classes: Vec<LoxClass>,
+    /// Where print statements write. Standard output unless the host redirects it.
+    output: Box<dyn Write>,
+    /// The script's global environment. `environment` is the same environment between runs.
+    globals: Rc<RefCell<Environment>>,
+    /// The global environment of the module whose code is running. Unresolved variables are read and written here.
+    module_globals: Rc<RefCell<Environment>>,
+    /// Whether a run is going. Calls from Rust made during a run do not start a new one.
+    running: bool,
+}
+
+/// Reading the clock on every step would slow the interpreter down, so the deadline is checked once per this many steps.
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// The default maximum call depth.
+///
+/// A Lox call takes about 7 to 12 KB of native stack in a release build and 25 to 50 KB in a debug build, more when its expressions nest deeply. So the default fits an 8 MB main thread in a release build, and the CLI runs on a thread big enough for any build. When a thread's stack runs low first, calls fail with the same stack overflow error.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// How much native stack has to be left for another Lox call to start. It is several times what one call takes in a debug build, so the check always comes before the stack runs out.
+const STACK_RESERVE: usize = 256 * 1024;
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        // the script's global environment. Unresolved variables are looked up at the root of the current environment chain.
         let environment = Rc::new(RefCell::new(Environment::new()));
 
-        let globals = environment.clone();
+        let interpreter = Self {
+            globals: Rc::clone(&environment),
+            module_globals: Rc::clone(&environment),
+            environment,
+            locals: Rc::new(RefCell::new(Locals::new())),
+            script_path: None,
+            modules: HashMap::new(),
+            loading: Vec::new(),
+            call_stack: Vec::new(),
+            call_site_line: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            fuel: None,
+            timeout: None,
+            steps: 0,
+            deadline: None,
+            timed_out: false,
+            natives: native_function::builtins(),
+            native_classes: Vec::new(),
+            output: Box::new(io::stdout()),
+            running: false,
+        };
+
+        interpreter.define_natives(&interpreter.environment);
+
+        interpreter
+    }
 
-        globals.borrow_mut().define(
-            "clock".to_string(),
-            Object::Callable(Callable::LoxNativeFunction(LoxNativeFunctions::Clock(
-                Clock::new(),
-            ))),
-        );
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
 
-        Self {
-            globals,
-            environment,
-            locals: Rc::new(RefCell::new(HashMap::new())),
+    /// # output
+    /// The writer print statements go to.
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.output.as_mut()
+    }
+
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// # step
+    /// Counts one step of the current run against its fuel and deadline. Once a limit is reached, every later step fails too, so a finally block cannot keep the script going.
+    fn step(&mut self) -> Result<(), LoxError> {
+        self.steps += 1;
+
+        if let Some(fuel) = self.fuel {
+            if self.steps > fuel {
+                return Err(LoxError::LimitError(LimitError::OutOfFuel(fuel)));
+            }
         }
+
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.timeout) {
+            if self.steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+                self.timed_out = true;
+            }
+            if self.timed_out {
+                return Err(LoxError::LimitError(LimitError::TimedOut(timeout)));
+            }
+        }
+
+        Ok(())
     }
 
-    pub fn interpret(&mut self, statements: &mut Vec<Stmt>) -> Result<Vec<Object>, LoxError> {
-        let mut results = Vec::new();
-        for statement in statements {
-            match self.execute(statement) {
-                Ok(value) => results.push(value),
-                Err(e) => return Err(e),
+    pub fn call_depth(&self) -> usize {
+        self.call_stack.len()
+    }
+
+    pub fn max_call_depth(&self) -> usize {
+        self.max_call_depth
+    }
+
+    /// # set_max_call_depth
+    /// Every Lox call uses some of the native stack, see `DEFAULT_MAX_CALL_DEPTH`. A limit the thread's stack cannot hold is cut short: calls fail with a stack overflow error once the stack runs low, whatever the limit.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// # check_call_depth
+    /// Fails with a stack overflow error at the token if one more Lox call would go deeper than the maximum call depth, or than the native stack can hold. Every path that calls a Lox function checks this first: calls, getters and setters.
+    pub fn check_call_depth(&self, token: &Token) -> Result<(), LoxError> {
+        let stack_is_low = stacker::remaining_stack().is_some_and(|left| left < STACK_RESERVE);
+
+        if self.call_depth() >= self.max_call_depth || stack_is_low {
+            return Err(LoxError::RuntimeError(RuntimeError::new(
+                "Stack overflow.".to_string(),
+                token,
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn call_site_line(&self) -> usize {
+        self.call_site_line
+    }
+
+    /// # in_frame
+    /// Runs a function body with its frame on the call stack. A runtime error that leaves the body without a backtrace gets the current stack as its backtrace.
+    pub fn in_frame<F>(&mut self, frame: CallFrame, body: F) -> Result<Object, LoxError>
+    where
+        F: FnOnce(&mut Interpreter) -> Result<Object, LoxError>,
+    {
+        self.call_stack.push(frame);
+
+        let result = body(self).map_err(|error| match error {
+            LoxError::RuntimeError(mut error) if error.backtrace().is_empty() => {
+                error.set_backtrace(self.call_stack.iter().rev().cloned().collect());
+                LoxError::RuntimeError(error)
             }
+            error => error,
+        });
+
+        self.call_stack.pop();
+
+        result
+    }
+
+    /// # define_natives
+    /// Defines the native functions and classes in a global environment. Every module gets its own copy.
+    fn define_natives(&self, globals: &Rc<RefCell<Environment>>) {
+        for native in &self.natives {
+            globals.borrow_mut().define(
+                native.name().to_string(),
+                Object::Callable(Callable::LoxNativeFunction(native.clone())),
+            );
         }
+        for class in &self.native_classes {
+            globals.borrow_mut().define(
+                class.name().clone(),
+                Object::Callable(Callable::LoxClass(class.clone())),
+            );
+        }
+    }
 
-        Ok(results)
+    /// # define_native
+    /// Exposes a Rust closure to Lox as a global function. Calls with the wrong number of arguments fail before the closure runs, and an `Err` it returns becomes a runtime error at the call.
+    ///
+    /// Modules imported later can call it too.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: u8,
+        function: impl Fn(&mut Interpreter, Vec<Object>) -> NativeResult + 'static,
+    ) {
+        self.add_native(LoxNativeFunction::new(name, arity, function));
     }
 
-    pub fn execute(&mut self, statement: &mut Stmt) -> Result<Object, LoxError> {
-        match statement.accept(self) {
-            Ok(value) => return Ok(value),
-            Err(e) => return Err(e),
+    /// # define_typed_native
+    /// Like `define_native`, but the function takes Rust arguments converted with `FromLox` and returns a value converted with `IntoLox`. Its arity is its number of parameters.
+    pub fn define_typed_native<Arguments>(
+        &mut self,
+        name: &str,
+        function: impl TypedNative<Arguments>,
+    ) {
+        self.add_native(function.into_native(name));
+    }
+
+    /// # define_native_class
+    /// Exposes a class whose methods are Rust closures. Scripts can subclass it with `<`, and its methods get the instance they are called on as `this`.
+    pub fn define_native_class(&mut self, name: &str, methods: Vec<NativeMethod>) {
+        let class = LoxClass::native(name.to_string(), methods);
+
+        self.current_globals().borrow_mut().define(
+            name.to_string(),
+            Object::Callable(Callable::LoxClass(class.clone())),
+        );
+        self.native_classes.push(class);
+    }
+
+    fn add_native(&mut self, native: LoxNativeFunction) {
+        self.current_globals().borrow_mut().define(
+            native.name().to_string(),
+            Object::Callable(Callable::LoxNativeFunction(native.clone())),
+        );
+        self.natives.push(native);
+    }
+
+    pub fn locals(&self) -> Rc<RefCell<Locals>> {
+        self.locals.clone()
+    }
+
+    /// # set_script_path
+    /// Records the file being run so that its imports are found next to it. The script counts as loading for as long as it runs, so a module importing it is reported as a cycle.
+    pub fn set_script_path(&mut self, path: &str) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+
+        self.loading.push(path.clone());
+        self.script_path = Some(path);
+    }
+
+    /// # interpret
+    /// Runs a script. The fuel and the timeout are counted from here.
+    ///
+    /// Returns the value of the last statement when it is an expression statement, and nil otherwise.
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<Object, LoxError> {
+        self.run(|interpreter| {
+            if let Some((Stmt::Expression { expression }, rest)) = statements.split_last() {
+                interpreter.execute_statements(rest)?;
+                return interpreter.evaluate(expression);
+            }
+
+            interpreter.execute_statements(statements)?;
+            Ok(Object::Nil)
+        })
+    }
+
+    /// # run
+    /// Runs the body with fresh limits. A run started during another one, like a native function calling back into Lox, shares the limits of the outer run.
+    fn run<T>(
+        &mut self,
+        body: impl FnOnce(&mut Interpreter) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        if self.running {
+            return body(self);
         }
+
+        self.steps = 0;
+        self.timed_out = false;
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        self.running = true;
+        let result = body(self);
+        self.running = false;
+
+        result
     }
 
-    pub fn resolve(&mut self, expr: &Expr, depth: usize) -> Result<Object, LoxError> {
-        self.locals.borrow_mut().insert(expr.clone(), depth);
+    fn execute_statements(&mut self, statements: &[Stmt]) -> Result<(), LoxError> {
+        for statement in statements {
+            // the resolver rejects return, break and continue at the top level, so every completion here is normal
+            self.execute(statement)?;
+        }
 
-        Ok(Object::Nil)
+        Ok(())
     }
 
+    pub fn execute(&mut self, statement: &Stmt) -> Result<Completion, LoxError> {
+        self.step()?;
+        statement.accept(self)
+    }
+
+    /// # execute_block_stmt
+    /// Runs the statements in the given environment. A statement that completes abruptly ends the block, and its completion is handed to the enclosing statement.
     pub fn execute_block_stmt(
         &mut self,
-        block_stmt: &mut BlockStmt,
+        statements: &[Stmt],
         enclosed_environment: Environment,
-    ) -> Result<Object, LoxError> {
+    ) -> Result<Completion, LoxError> {
         let previous = self.environment.clone();
 
         self.environment = Rc::new(RefCell::new(enclosed_environment));
 
-        for statement in &mut block_stmt.statements {
-            match self.execute(statement) {
-                Ok(_) => {}
-                Err(e) => match e {
-                    LoxError::LoxReturn(return_value) => {
-                        self.environment = previous;
-                        return Ok(return_value.value.unwrap_or(Object::Nil));
-                    }
-                    _ => {
-                        return Err(e);
-                    }
-                },
+        let mut completion = Ok(Completion::Normal);
+        for statement in statements {
+            completion = self.execute(statement);
+            if !matches!(completion, Ok(Completion::Normal)) {
+                break;
             }
         }
 
         self.environment = previous;
 
-        Ok(Object::Nil)
+        completion
     }
 
     pub fn evaluate(&mut self, expression: &Expr) -> Result<Object, LoxError> {
+        self.step()?;
+
         match expression.accept(self) {
             Ok(value) => return Ok(value),
             Err(e) => return Err(e),
         }
     }
 
-    fn is_equal(&self, a: &Object, b: &Object) -> bool {
-        match (a, b) {
-            (Object::Num(a), Object::Num(b)) => a == b,
-            (Object::Str(a), Object::Str(b)) => a == b,
-            (Object::Bool(a), Object::Bool(b)) => a == b,
-            _ => false,
-        }
-    }
-
     fn check_number_operand(&self, token: &Token, operand: Object) -> Result<Object, LoxError> {
         match operand {
             Object::Num(_) => Ok(operand),
@@ -137,20 +421,312 @@ impl Interpreter {
         }
     }
 
-    fn look_up_variable(&self, name: &Token, expr: &Expr) -> Result<Object, LoxError> {
-        let locals = self.locals.borrow();
-        let distance = locals.get(&expr);
+    /// # list_index
+    /// Converts an index value into a position in a list of the given length. Only whole numbers inside the list are valid indexes.
+    fn list_index(&self, bracket: &Token, index: Object, length: usize) -> Result<usize, LoxError> {
+        match index {
+            Object::Num(index) if index.fract() == 0.0 => {
+                if index >= 0.0 && (index as usize) < length {
+                    Ok(index as usize)
+                } else {
+                    Err(LoxError::RuntimeError(RuntimeError::new(
+                        format!(
+                            "Index {} is out of range for a list of length {}.",
+                            index, length
+                        ),
+                        bracket,
+                    )))
+                }
+            }
+            _ => Err(LoxError::RuntimeError(RuntimeError::new(
+                "List index must be a whole number.".to_string(),
+                bracket,
+            ))),
+        }
+    }
+
+    /// # map_key
+    /// Converts an index value into a map key, reporting unhashable keys at the bracket.
+    fn map_key(&self, bracket: &Token, key: &Object) -> Result<MapKey, LoxError> {
+        MapKey::from_object(key)
+            .map_err(|message| LoxError::RuntimeError(RuntimeError::new(message, bracket)))
+    }
+
+    /// # caught_value
+    /// The value a catch clause binds. Thrown values are handed over as they are. Errors raised by the interpreter become an `Error` instance with `message` and `line` fields.
+    fn caught_value(&self, error: RuntimeError) -> Object {
+        if let Some(value) = error.thrown_value() {
+            return value.clone();
+        }
 
-        match distance {
-            Some(distance) => {
-                let value = self.environment.borrow().get_at(*distance, name);
+        let (message, token) = error.get_error();
+        let class = LoxClass::new("Error".to_string(), None, None, None, None);
+        let instance = LoxInstance::new(Rc::new(RefCell::new(class)));
+
+        instance
+            .fields
+            .borrow_mut()
+            .insert("message".to_string(), Object::Str(script_message(message)));
+        instance
+            .fields
+            .borrow_mut()
+            .insert("line".to_string(), Object::Num(token.line as f64));
+
+        Object::Instance(instance)
+    }
+
+    /// # call_object
+    /// Calls the value with the arguments after checking their number. The paren token is where errors are reported.
+    fn call_object(
+        &mut self,
+        callee: Object,
+        arguments: Vec<Object>,
+        paren: &Token,
+    ) -> Result<Object, LoxError> {
+        match callee {
+            Object::Callable(function) => {
+                if arguments.len() != function.arity() as usize {
+                    return Err(LoxError::RuntimeError(RuntimeError::new(
+                        format!(
+                            "Expected {} arguments but got {}.",
+                            function.arity(),
+                            arguments.len()
+                        ),
+                        paren,
+                    )));
+                }
+
+                self.call_site_line = paren.line;
+                function.call(self, arguments, paren)
+            }
+            Object::Instance(_instance) => Ok(Object::Nil),
+            _ => Err(LoxError::RuntimeError(RuntimeError::new(
+                "Can only call functions and classes. -- Interpreter: visit_call_expr()"
+                    .to_string(),
+                paren,
+            ))),
+        }
+    }
+
+    /// # globals
+    /// The globals of the script, by name. Functions and classes the script declared can be called from Rust with call_function.
+    pub fn globals(&self) -> Ref<'_, HashMap<String, Object>> {
+        Ref::map(self.globals.borrow(), |globals| &globals.values)
+    }
+
+    /// # call_function
+    /// Calls a global function or class of the script from Rust, such as an event handler, and returns its result.
+    ///
+    /// A call made from a native function counts against the limits of the run the native was called from. Otherwise the call is a run of its own.
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxError> {
+        let token = Self::host_call_token(name);
+        let function = self.globals().get(name).cloned().ok_or_else(|| {
+            LoxError::RuntimeError(RuntimeError::new(
+                format!("Undefined variable '{}'.", name),
+                &token,
+            ))
+        })?;
+
+        self.run(|interpreter| interpreter.call_object(function, arguments, &token))
+    }
+
+    /// # call_method
+    /// Calls a method of an instance from Rust, with the instance bound to `this`. Native methods and fields holding functions can be called too.
+    pub fn call_method(
+        &mut self,
+        instance: &Object,
+        name: &str,
+        arguments: Vec<Object>,
+    ) -> Result<Object, LoxError> {
+        let token = Self::host_call_token(name);
+        let method = match instance {
+            Object::Instance(instance) => instance.get(&token).map_err(LoxError::RuntimeError)?,
+            _ => {
+                return Err(LoxError::RuntimeError(RuntimeError::new(
+                    format!("Only instances have methods, not {}.", instance.type_name()),
+                    &token,
+                )))
+            }
+        };
+
+        self.run(|interpreter| interpreter.call_object(method, arguments, &token))
+    }
+
+    /// Calls from Rust have no place in the source, so their errors are reported at line 0.
+    fn host_call_token(name: &str) -> Token {
+        Token::new(
+            TokenType::Identifier,
+            name.to_string(),
+            None,
+            0,
+            SYNTHETIC_ID,
+        )
+    }
+
+    /// # current_globals
+    /// The global scope of the module the running code was declared in.
+    fn current_globals(&self) -> &Rc<RefCell<Environment>> {
+        &self.module_globals
+    }
+
+    /// # in_module
+    /// Runs the body with the globals of another module as the current globals. Functions run in the module they were declared in, wherever they are called from.
+    pub fn in_module<T>(
+        &mut self,
+        globals: Rc<RefCell<Environment>>,
+        body: impl FnOnce(&mut Interpreter) -> T,
+    ) -> T {
+        let previous = std::mem::replace(&mut self.module_globals, globals);
+        let result = body(self);
+        self.module_globals = previous;
+
+        result
+    }
+
+    /// # load_module
+    /// Returns the global environment of a module, running the module first if it has not been loaded yet.
+    ///
+    /// Errors that stop the module from loading are reported at the import keyword. Runtime errors raised while the module runs are passed on unchanged.
+    fn load_module(
+        &mut self,
+        import_stmt: &ImportStmt,
+    ) -> Result<Rc<RefCell<Environment>>, LoxError> {
+        let module_name = import_stmt.module_name();
+        let module_error = |message: String| {
+            LoxError::RuntimeError(RuntimeError::new(message, &import_stmt.keyword))
+        };
+
+        let directory = match &self.script_path {
+            Some(script_path) => script_path.parent().map(Path::to_path_buf),
+            None => None,
+        };
+        let path = fs::canonicalize(directory.unwrap_or_default().join(&module_name)).map_err(
+            |error| module_error(format!("Cannot find module '{}': {}.", module_name, error)),
+        )?;
+
+        if let Some(module) = self.modules.get(&path) {
+            return Ok(module.clone());
+        }
+
+        if let Some(start) = self.loading.iter().position(|loading| *loading == path) {
+            let cycle: Vec<String> = self.loading[start..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|path| file_name(path))
+                .collect();
+            return Err(module_error(format!(
+                "Import cycle detected: {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        let source = fs::read_to_string(&path).map_err(|error| {
+            module_error(format!("Cannot read module '{}': {}.", module_name, error))
+        })?;
+
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new(source, error_reporter.clone());
+        let tokens = scanner.scan_tokens();
+        if error_reporter.borrow().had_error() {
+            return Err(module_error(format!(
+                "Module '{}' could not be scanned.",
+                module_name
+            )));
+        }
+
+        let statements = Parser::new(tokens).parse().map_err(|errors| {
+            let errors: Vec<String> = errors
+                .iter()
+                .map(|error| {
+                    let (message, token) = error.get_error();
+                    format!(
+                        "Parse error in module '{}' [line {}] at '{}': {}",
+                        module_name, token.line, token.lexeme, message
+                    )
+                })
+                .collect();
+            module_error(errors.join("\n"))
+        })?;
+
+        Resolver::new(self.locals.clone())
+            .resolve(&statements)
+            .map_err(|error| match error {
+                LoxError::RuntimeError(error) => {
+                    let (message, token) = error.get_error();
+                    module_error(format!(
+                        "Error in module '{}' [line {}]: {}",
+                        module_name, token.line, message
+                    ))
+                }
+                error => error,
+            })?;
+
+        let module = Rc::new(RefCell::new(Environment::new()));
+        self.define_natives(&module);
+
+        // run the module in its own globals, then go back to the importer
+        let previous_environment = std::mem::replace(&mut self.environment, module.clone());
+        let previous_path = self.script_path.replace(path.clone());
+        self.loading.push(path.clone());
+
+        let result = self.in_module(module.clone(), |interpreter| {
+            interpreter.execute_statements(&statements)
+        });
+
+        self.loading.pop();
+        self.script_path = previous_path;
+        self.environment = previous_environment;
+
+        result?;
+        self.modules.insert(path, module.clone());
+
+        Ok(module)
+    }
+
+    /// # module_exports
+    /// The top-level names a module defines. The natives every module starts with are not exported.
+    fn module_exports(&self, module: &Rc<RefCell<Environment>>) -> HashMap<String, Object> {
+        module
+            .borrow()
+            .values
+            .iter()
+            .filter(|(_, value)| !self.is_native(value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Whether the value is one of the natives every module starts with.
+    fn is_native(&self, value: &Object) -> bool {
+        match value {
+            Object::Callable(Callable::LoxNativeFunction(_)) => true,
+            Object::Callable(Callable::LoxClass(class)) => self
+                .native_classes
+                .iter()
+                .any(|native| native.id() == class.id()),
+            _ => false,
+        }
+    }
+
+    fn look_up_variable(&self, name: &Token) -> Result<Object, LoxError> {
+        let local = self.locals.borrow().get(name.id);
+
+        match local {
+            Some(local) => {
+                let value = self
+                    .environment
+                    .borrow()
+                    .get_at(local.depth, local.slot, name);
                 match value {
                     Ok(value) => Ok(value),
                     Err(e) => Err(LoxError::RuntimeError(e)),
                 }
             }
             None => {
-                let value = self.globals.borrow().get_value(name);
+                let value = self.current_globals().borrow().get_value(name);
                 match value {
                     Ok(value) => Ok(value),
                     Err(e) => Err(LoxError::RuntimeError(e)),
@@ -164,10 +740,6 @@ impl Interpreter {
         superclass: Option<LoxClass>,
         class_stmt: &ClassStmt,
     ) -> Result<Object, LoxError> {
-        self.environment
-            .borrow_mut()
-            .define(class_stmt.name.lexeme.clone(), Object::Nil);
-
         if let Some(superclass) = superclass.clone() {
             self.environment = Rc::new(RefCell::new(Environment::with_enclosing(
                 self.environment.clone(),
@@ -180,14 +752,35 @@ impl Interpreter {
         }
 
         let mut methods = HashMap::new();
+        let mut setters = HashMap::new();
 
         for method in class_stmt.methods.clone() {
             let lox_function = LoxFunction::new(
                 &method,
                 self.environment.clone(),
                 method.name.lexeme == "init",
+                Some(class_stmt.name.lexeme.clone()),
+            );
+            let table = match method.kind {
+                FunType::Setter => &mut setters,
+                _ => &mut methods,
+            };
+            table.insert(
+                method.name.lexeme.clone(),
+                Object::Callable(Callable::LoxFunction(lox_function)),
+            );
+        }
+
+        let mut static_methods = HashMap::new();
+
+        for method in class_stmt.class_methods.clone() {
+            let lox_function = LoxFunction::new(
+                &method,
+                self.environment.clone(),
+                false,
+                Some(class_stmt.name.lexeme.clone()),
             );
-            methods.insert(
+            static_methods.insert(
                 method.name.lexeme.clone(),
                 Object::Callable(Callable::LoxFunction(lox_function)),
             );
@@ -197,24 +790,24 @@ impl Interpreter {
             class_stmt.name.lexeme.clone(),
             superclass.clone(),
             Some(methods),
+            Some(setters),
+            Some(static_methods),
         );
 
         if let Some(_superclass) = superclass.clone() {
-            let enclosing = self.environment.borrow().clone().enclosing;
+            let enclosing = self.environment.borrow().enclosing.clone();
             if let Some(enclosing) = enclosing {
                 self.environment = enclosing;
             }
         }
 
-        let assignment = self.environment.borrow_mut().assign(
-            &class_stmt.name,
+        // methods only look the class up when they are called, so it can be defined after they close over the environment
+        self.environment.borrow_mut().define(
+            class_stmt.name.lexeme.clone(),
             Object::Callable(Callable::LoxClass(class)),
         );
 
-        match assignment {
-            Ok(_) => Ok(Object::Nil),
-            Err(e) => Err(LoxError::RuntimeError(e)),
-        }
+        Ok(Object::Nil)
     }
 }
 
@@ -230,15 +823,8 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
 
         match (operator.token_type, left.clone(), right.clone()) {
             // Handle equals
-            (TokenType::BangEqual, left_num, right_num) => {
-                Ok(Object::Bool(!self.is_equal(&left_num, &right_num)))
-            }
-            (TokenType::EqualEqual, left_num, right_num) => {
-                match self.check_number_operands(operator, left, right) {
-                    Ok(_) => Ok(Object::Bool(self.is_equal(&left_num, &right_num))),
-                    Err(e) => Err(e),
-                }
-            }
+            (TokenType::BangEqual, _, _) => Ok(Object::Bool(!left.is_equal(&right))),
+            (TokenType::EqualEqual, _, _) => Ok(Object::Bool(left.is_equal(&right))),
 
             // Handle greater than
             (TokenType::Greater, Object::Num(left_num), Object::Num(right_num)) => {
@@ -334,15 +920,7 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
             .map(|argument| self.evaluate(argument))
             .collect::<Result<Vec<Object>, LoxError>>()?;
 
-        match processed_callee {
-            Object::Callable(function) => function.call(self, processed_arguments),
-            Object::Instance(_instance) => Ok(Object::Nil),
-            _ => Err(LoxError::RuntimeError(RuntimeError::new(
-                "Can only call functions and classes. -- Interpreter: visit_call_expr()"
-                    .to_string(),
-                paren,
-            ))),
-        }
+        self.call_object(processed_callee, processed_arguments, paren)
     }
 
     fn visit_get_expr(&mut self, raw_object: &Expr, name: &Token) -> Result<Object, LoxError> {
@@ -355,6 +933,8 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
                         Object::Callable(callable) => match callable {
                             Callable::LoxFunction(fun) => {
                                 if fun.is_getter {
+                                    self.check_call_depth(name)?;
+                                    self.call_site_line = name.line;
                                     return fun.call(self, vec![]);
                                 }
                                 Ok(Object::Callable(Callable::LoxFunction(fun)))
@@ -367,8 +947,22 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
                 }
                 Err(e) => Err(LoxError::RuntimeError(e)),
             },
+            Object::Callable(Callable::LoxClass(class)) => {
+                match class.find_static_method(&name.lexeme) {
+                    Some(method) => Ok(Object::Callable(Callable::LoxFunction(method))),
+                    None => Err(LoxError::RuntimeError(RuntimeError::new(
+                        format!(
+                            "Undefined static method '{}' on class {}.",
+                            name.lexeme,
+                            class.name()
+                        ),
+                        name,
+                    ))),
+                }
+            }
             _ => Err(LoxError::RuntimeError(RuntimeError::new(
-                "Only instances have properties. -- Interpreter: visit_get_expr()".to_string(),
+                "Only instances and classes have properties. -- Interpreter: visit_get_expr()"
+                    .to_string(),
                 name,
             ))),
         }
@@ -378,13 +972,123 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
         self.evaluate(expression)
     }
 
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+    ) -> Result<Object, LoxError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+
+        match object {
+            Object::List(list) => {
+                let position = self.list_index(bracket, index, list.borrow().len())?;
+                let element = list.borrow()[position].clone();
+                Ok(element)
+            }
+            Object::Map(map) => {
+                let key = self.map_key(bracket, &index)?;
+                let value = map.borrow().get(&key).cloned();
+                value.ok_or_else(|| {
+                    LoxError::RuntimeError(RuntimeError::new(
+                        format!("Key '{}' not found in map.", index),
+                        bracket,
+                    ))
+                })
+            }
+            _ => Err(LoxError::RuntimeError(RuntimeError::new(
+                "Only lists and maps can be indexed.".to_string(),
+                bracket,
+            ))),
+        }
+    }
+
+    fn visit_index_set_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<Object, LoxError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+
+        match object {
+            Object::List(list) => {
+                let position = self.list_index(bracket, index, list.borrow().len())?;
+                let value = self.evaluate(value)?;
+                list.borrow_mut()[position] = value.clone();
+                Ok(value)
+            }
+            Object::Map(map) => {
+                let key = self.map_key(bracket, &index)?;
+                let value = self.evaluate(value)?;
+                map.borrow_mut().insert(key, value.clone());
+                Ok(value)
+            }
+            _ => Err(LoxError::RuntimeError(RuntimeError::new(
+                "Only lists and maps can be indexed.".to_string(),
+                bracket,
+            ))),
+        }
+    }
+
+    fn visit_list_expr(&mut self, _bracket: &Token, elements: &[Expr]) -> Result<Object, LoxError> {
+        let elements = elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<Vec<Object>, LoxError>>()?;
+
+        Ok(Object::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_map_expr(
+        &mut self,
+        brace: &Token,
+        entries: &[(Expr, Expr)],
+    ) -> Result<Object, LoxError> {
+        let mut map = LoxMap::new();
+
+        for (key, value) in entries {
+            let key = self.evaluate(key)?;
+            let key = self.map_key(brace, &key)?;
+            let value = self.evaluate(value)?;
+            map.insert(key, value);
+        }
+
+        Ok(Object::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn visit_lambda_expr(&mut self, declaration: &FunStmt) -> Result<Object, LoxError> {
+        // the lambda closes over the environment it is evaluated in, just like a function declaration
+        let lox_function = LoxFunction::new(declaration, self.environment.clone(), false, None);
+
+        Ok(Object::Callable(Callable::LoxFunction(lox_function)))
+    }
+
+    /// ## visit_interpolation_expr
+    /// Each part is formatted with Display, like `print` does, so numbers, lists and instances can be interpolated as well as strings.
+    fn visit_interpolation_expr(
+        &mut self,
+        _quote: &Token,
+        parts: &[Expr],
+    ) -> Result<Object, LoxError> {
+        let mut string = String::new();
+        for part in parts {
+            string.push_str(&self.evaluate(part)?.to_string());
+        }
+
+        Ok(Object::Str(string))
+    }
+
     fn visit_literal_expr(&mut self, value: &Option<Object>) -> Result<Object, LoxError> {
         let empty_token = Token::new(
             TokenType::Nil,
             "".to_string(),
             Some(Object::Nil),
             0,
-            generate_id(),
+            SYNTHETIC_ID,
         );
 
         match value {
@@ -419,25 +1123,15 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
 
     fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Result<Object, LoxError> {
         let right_object = self.evaluate(right)?;
-        let empty_token = Token::new(
-            TokenType::Nil,
-            "".to_string(),
-            Some(Object::Nil),
-            0,
-            generate_id(),
-        );
 
-        match (operator.token_type, right_object.clone()) {
-            (TokenType::Minus, Object::Num(num)) => {
-                match self.check_number_operand(operator, right_object) {
-                    Ok(_result) => Ok(Object::Num(-num)),
-                    Err(e) => Err(e),
-                }
-            }
-            (TokenType::Bang, _) => Ok(Object::Bool(!right_object.is_truthy())),
+        match (operator.token_type, right_object) {
+            (TokenType::Minus, Object::Num(num)) => Ok(Object::Num(-num)),
+            // anything else is not a number, so the check reports the error at the operator
+            (TokenType::Minus, operand) => self.check_number_operand(operator, operand),
+            (TokenType::Bang, operand) => Ok(Object::Bool(!operand.is_truthy())),
             _ => Err(LoxError::RuntimeError(RuntimeError::new(
-                "No value".to_string(),
-                &empty_token,
+                format!("Unknown unary operator '{}'.", operator.lexeme),
+                operator,
             ))),
         }
     }
@@ -453,6 +1147,15 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
         match object {
             Object::Instance(mut instance) => {
                 let value_obj = self.evaluate(value)?;
+
+                // a setter takes the place of writing the field
+                if let Some(setter) = instance.find_setter(name) {
+                    self.check_call_depth(name)?;
+                    self.call_site_line = name.line;
+                    setter.call(self, vec![value_obj.clone()])?;
+                    return Ok(value_obj);
+                }
+
                 match instance.set(name, value_obj) {
                     Some(return_val) => Ok(return_val),
                     None => Ok(Object::Nil),
@@ -467,25 +1170,21 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
         }
     }
 
-    fn visit_super_expr(
-        &mut self,
-        expr: &Expr,
-        keyword: &Token,
-        method: &Token,
-    ) -> Result<Object, LoxError> {
-        let locals = self.locals.borrow();
+    fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> Result<Object, LoxError> {
+        let local = self.locals.borrow().get(keyword.id);
 
-        if let Some(distance) = locals.get(expr) {
+        if let Some(local) = local {
             let superclass = self
                 .environment
                 .borrow()
-                .get_at(*distance, &create_super_token(None, Some(keyword.line)))
+                .get_at(local.depth, local.slot, keyword)
                 .map_err(|err| LoxError::RuntimeError(err))?;
 
+            // the environment a method is bound to holds nothing but `this`, one step inside the one holding `super`
             let object = self
                 .environment
                 .borrow()
-                .get_at(*distance - 1, &create_this_token(None, Some(method.line)))
+                .get_at(local.depth - 1, 0, keyword)
                 .map_err(|err| LoxError::RuntimeError(err))?;
 
             let callable_superclass = match superclass {
@@ -503,6 +1202,12 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
                     // "This is almost exactly like the code for looking up a method of a get expression, except that we call findMethod() on the superclass instead of on the class of the current object."
                     return Ok(Object::Callable(Callable::LoxFunction(method.bind(object))));
                 }
+            } else if let Some(method) = callable_superclass.find_native_method(&method.lexeme) {
+                if let Object::Instance(object) = object {
+                    return Ok(Object::Callable(Callable::LoxNativeFunction(
+                        method.bind(object),
+                    )));
+                }
             } else {
                 return Err(LoxError::RuntimeError(RuntimeError::new(
                     format!("Undefined property '{}'.", method.lexeme),
@@ -514,8 +1219,8 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
         Ok(Object::Nil)
     }
 
-    fn visit_this_expr(&mut self, expr: &Expr, keyword: &Token) -> Result<Object, LoxError> {
-        return self.look_up_variable(keyword, expr);
+    fn visit_this_expr(&mut self, keyword: &Token) -> Result<Object, LoxError> {
+        return self.look_up_variable(keyword);
     }
 
     /// ## visit_assign_expr
@@ -523,38 +1228,38 @@ impl ExprVisitor<Result<Object, LoxError>> for Interpreter {
     fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Result<Object, LoxError> {
         let value_obj = self.evaluate(value)?;
 
-        let locals = self.locals.borrow();
-        let distance = locals.get(&value);
-        match distance {
-            Some(distance) => self
+        let local = self.locals.borrow().get(name.id);
+        match local {
+            Some(local) => self
                 .environment
                 .borrow_mut()
-                .assign_at(*distance, name, value_obj)
+                .assign_at(local.depth, local.slot, name, value_obj)
                 .or_else(|error| Err(LoxError::RuntimeError(error))),
             None => self
-                .globals
+                .current_globals()
                 .borrow_mut()
                 .assign(name, value_obj)
                 .or_else(|error| Err(LoxError::RuntimeError(error))),
         }
     }
 
-    fn visit_variable_expr(&mut self, expr: &Expr, name: &Token) -> Result<Object, LoxError> {
-        self.look_up_variable(name, expr)
+    fn visit_variable_expr(&mut self, name: &Token) -> Result<Object, LoxError> {
+        self.look_up_variable(name)
     }
 }
 
-impl StmtVisitor<Result<Object, LoxError>> for Interpreter {
-    fn visit_expression_stmt(&mut self, statement: &Expr) -> Result<Object, LoxError> {
-        self.evaluate(statement)
+impl StmtVisitor<Result<Completion, LoxError>> for Interpreter {
+    fn visit_expression_stmt(&mut self, statement: &Expr) -> Result<Completion, LoxError> {
+        self.evaluate(statement)?;
+        Ok(Completion::Normal)
     }
 
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
-        then_branch: &mut Stmt,
-        else_branch: &mut Option<Box<Stmt>>,
-    ) -> Result<Object, LoxError> {
+        then_branch: &Stmt,
+        else_branch: &Option<Box<Stmt>>,
+    ) -> Result<Completion, LoxError> {
         match self.evaluate(condition) {
             Ok(value) => {
                 if value.is_truthy() {
@@ -565,69 +1270,170 @@ impl StmtVisitor<Result<Object, LoxError>> for Interpreter {
             }
             Err(e) => return Err(e),
         }
-        Ok(Object::Nil)
+        Ok(Completion::Normal)
     }
 
-    fn visit_print_stmt(&mut self, statement: &Expr) -> Result<Object, LoxError> {
+    fn visit_print_stmt(
+        &mut self,
+        keyword: &Token,
+        statement: &Expr,
+    ) -> Result<Completion, LoxError> {
         let value = self.evaluate(statement)?;
-        println!("{}", value);
-        Ok(Object::Nil)
+
+        writeln!(self.output, "{}", value).map_err(|error| {
+            LoxError::RuntimeError(RuntimeError::new(
+                format!("Could not print: {}.", error),
+                keyword,
+            ))
+        })?;
+
+        Ok(Completion::Normal)
     }
 
-    fn visit_return_stmt(&mut self, _token: &Token, value: &Expr) -> Result<Object, LoxError> {
+    fn visit_return_stmt(&mut self, _token: &Token, value: &Expr) -> Result<Completion, LoxError> {
         let value = self.evaluate(value)?;
 
-        // throw an error to trigger an escape from deep call stack
-        Err(LoxError::LoxReturn(LoxReturn::new(Some(value))))
+        Ok(Completion::Return(Box::new(value)))
+    }
+
+    fn visit_throw_stmt(&mut self, keyword: &Token, value: &Expr) -> Result<Completion, LoxError> {
+        let value = self.evaluate(value)?;
+
+        Err(LoxError::RuntimeError(RuntimeError::thrown(value, keyword)))
+    }
+
+    fn visit_import_stmt(&mut self, import_stmt: &ImportStmt) -> Result<Completion, LoxError> {
+        let module = self.load_module(import_stmt)?;
+        let exports = self.module_exports(&module);
+        let module_name = import_stmt.module_name();
+
+        if let Some(alias) = &import_stmt.alias {
+            // the namespace is an instance of a class named after the module file
+            let class_name = Path::new(&module_name)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| module_name.to_string());
+            let class = LoxClass::new(class_name, None, None, None, None);
+            let namespace = LoxInstance::new(Rc::new(RefCell::new(class)));
+            namespace.fields.borrow_mut().extend(exports.clone());
+
+            self.environment
+                .borrow_mut()
+                .define(alias.lexeme.clone(), Object::Instance(namespace));
+        }
+
+        for name in &import_stmt.names {
+            match exports.get(&name.lexeme) {
+                Some(value) => self
+                    .environment
+                    .borrow_mut()
+                    .define(name.lexeme.clone(), value.clone()),
+                None => {
+                    return Err(LoxError::RuntimeError(RuntimeError::new(
+                        format!(
+                            "Module '{}' has no export named '{}'.",
+                            module_name, name.lexeme
+                        ),
+                        name,
+                    )))
+                }
+            }
+        }
+
+        Ok(Completion::Normal)
     }
 
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &mut Stmt) -> Result<Object, LoxError> {
+    fn visit_try_stmt(&mut self, try_stmt: &TryStmt) -> Result<Completion, LoxError> {
+        let mut result = self.visit_block_stmt(&try_stmt.body);
+
+        // only errors are caught. Returns and loop control are completions, so they pass through, but still run the finally block
+        if let Some(catch) = &try_stmt.catch {
+            if let Err(LoxError::RuntimeError(error)) = result {
+                let mut environment = Environment::with_enclosing(self.environment.clone());
+                environment.define(catch.name.lexeme.clone(), self.caught_value(error));
+
+                result = self.execute_block_stmt(&catch.body.statements, environment);
+            }
+        }
+
+        // an error or an abrupt completion of the finally block replaces the outcome of the try and catch blocks
+        if let Some(finally) = &try_stmt.finally {
+            match self.visit_block_stmt(finally)? {
+                Completion::Normal => (),
+                completion => return Ok(completion),
+            }
+        }
+
+        result
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) -> Result<Completion, LoxError> {
         while self.evaluate(condition)?.is_truthy() {
-            match self.execute(body) {
-                Ok(_) => (),
-                Err(e) => return Err(e),
+            match self.execute(body)? {
+                Completion::Normal | Completion::Continue => (),
+                Completion::Break => break,
+                completion @ Completion::Return(_) => return Ok(completion),
+            }
+
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
             }
         }
 
-        Ok(Object::Nil)
+        Ok(Completion::Normal)
+    }
+
+    fn visit_break_stmt(&mut self, _keyword: &Token) -> Result<Completion, LoxError> {
+        // unwind to the enclosing loop, the same way return unwinds to the enclosing function
+        Ok(Completion::Break)
     }
 
-    fn visit_var_stmt(&mut self, name: &Token, initializer: &Expr) -> Result<Object, LoxError> {
+    fn visit_continue_stmt(&mut self, _keyword: &Token) -> Result<Completion, LoxError> {
+        Ok(Completion::Continue)
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: &Expr) -> Result<Completion, LoxError> {
         match self.evaluate(initializer) {
             Ok(value) => {
                 self.environment
                     .borrow_mut()
-                    .define(name.lexeme.clone(), value.clone());
+                    .define(name.lexeme.clone(), value);
 
-                Ok(value)
+                Ok(Completion::Normal)
             }
             Err(e) => Err(e),
         }
     }
 
-    fn visit_block_stmt(&mut self, statements: &mut BlockStmt) -> Result<Object, LoxError> {
+    fn visit_block_stmt(&mut self, statements: &BlockStmt) -> Result<Completion, LoxError> {
         self.execute_block_stmt(
-            statements,
+            &statements.statements,
             Environment::with_enclosing(self.environment.clone()),
         )
     }
 
-    fn visit_function_stmt(&mut self, declaration: &mut FunStmt) -> Result<Object, LoxError> {
-        let lox_function = LoxFunction::new(declaration, self.environment.clone(), false);
+    fn visit_function_stmt(&mut self, declaration: &FunStmt) -> Result<Completion, LoxError> {
+        let lox_function = LoxFunction::new(declaration, self.environment.clone(), false, None);
         self.environment.borrow_mut().define(
             declaration.name.lexeme.clone(),
             Object::Callable(Callable::LoxFunction(lox_function)),
         );
 
-        Ok(Object::Nil)
+        Ok(Completion::Normal)
     }
 
-    fn visit_class_stmt(&mut self, class_stmt: &ClassStmt) -> Result<Object, LoxError> {
+    fn visit_class_stmt(&mut self, class_stmt: &ClassStmt) -> Result<Completion, LoxError> {
         if let Some(superclass_var) = &class_stmt.superclass {
             let superclass = Expr::Variable(superclass_var.clone());
             match self.evaluate(&superclass) {
                 Ok(Object::Callable(Callable::LoxClass(superclass))) => {
-                    return self.handle_class_creation(Some(superclass), class_stmt);
+                    self.handle_class_creation(Some(superclass), class_stmt)?;
+                    return Ok(Completion::Normal);
                 }
                 _ => {
                     return Err(LoxError::RuntimeError(RuntimeError::new(
@@ -638,6 +1444,430 @@ impl StmtVisitor<Result<Object, LoxError>> for Interpreter {
             };
         }
 
-        self.handle_class_creation(None, class_stmt)
+        self.handle_class_creation(None, class_stmt)?;
+        Ok(Completion::Normal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::generate_id;
+
+    /// Runs the source and then evaluates the expression, returning its value.
+    fn evaluate_after(source: &str, expression: &str) -> Result<Object, LoxError> {
+        evaluate_in(&mut Interpreter::new(), source, expression)
+    }
+
+    fn evaluate_in(
+        interpreter: &mut Interpreter,
+        source: &str,
+        expression: &str,
+    ) -> Result<Object, LoxError> {
+        let source = format!("{}\nvar result = {};", source, expression);
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+
+        let mut scanner = Scanner::new(source, error_reporter);
+        let tokens = scanner.scan_tokens();
+        let statements = Parser::new(tokens)
+            .parse()
+            .map_err(|mut errors| LoxError::ParseError(errors.remove(0)))?;
+
+        Resolver::new(interpreter.locals()).resolve(&statements)?;
+        interpreter.interpret(&statements)?;
+
+        let result = Token::new(
+            TokenType::Identifier,
+            "result".to_string(),
+            None,
+            1,
+            generate_id(),
+        );
+        let value = interpreter.environment.borrow().get_value(&result);
+        value.map_err(LoxError::RuntimeError)
+    }
+
+    fn assert_comparisons(source: &str, cases: &[(&str, bool)]) {
+        for (expression, expected) in cases {
+            match evaluate_after(source, expression) {
+                Ok(Object::Bool(value)) => {
+                    assert_eq!(value, *expected, "`{}` should be {}", expression, expected)
+                }
+                Ok(other) => panic!("`{}` evaluated to {:?}", expression, other),
+                Err(_) => panic!("`{}` raised an error", expression),
+            }
+        }
+    }
+
+    #[test]
+    fn test_equality_of_primitives() {
+        assert_comparisons(
+            "",
+            &[
+                ("1 == 1", true),
+                ("1 == 2", false),
+                ("0.5 == 1 / 2", true),
+                ("-0 == 0", true),
+                ("\"a\" == \"a\"", true),
+                ("\"a\" == \"b\"", false),
+                ("\"\" == \"\"", true),
+                ("true == true", true),
+                ("true == false", false),
+                ("nil == nil", true),
+                ("1 != 2", true),
+                ("\"a\" != \"a\"", false),
+                ("nil != nil", false),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_nan_is_not_equal_to_itself() {
+        assert_comparisons(
+            "var nan = 0 / 0;",
+            &[("nan == nan", false), ("nan != nan", true)],
+        );
+    }
+
+    #[test]
+    fn test_values_of_different_types_are_never_equal() {
+        assert_comparisons(
+            "class A {} fun f() {}",
+            &[
+                ("1 == \"1\"", false),
+                ("0 == false", false),
+                ("nil == false", false),
+                ("\"\" == nil", false),
+                ("\"true\" == true", false),
+                ("A == A()", false),
+                ("f == nil", false),
+                ("[] == nil", false),
+                ("1 != \"1\"", true),
+                ("nil != false", true),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_instances_compare_by_identity() {
+        assert_comparisons(
+            "class Point { init(x) { this.x = x; } }
+            var a = Point(1);
+            var b = Point(1);
+            var c = a;
+            class Box { init(value) { this.value = value; } }
+            var box = Box(a);",
+            &[
+                ("a == a", true),
+                ("a == b", false),
+                ("a == c", true),
+                ("a != b", true),
+                ("box.value == a", true),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_an_instance_is_equal_to_this() {
+        assert_comparisons(
+            "class A { me() { return this; } }
+            var a = A();",
+            &[("a.me() == a", true), ("A().me() == a", false)],
+        );
+    }
+
+    #[test]
+    fn test_classes_compare_by_identity() {
+        assert_comparisons(
+            "class A {}
+            class B {}
+            class C < A {}
+            var alias = A;",
+            &[
+                ("A == A", true),
+                ("A == alias", true),
+                ("A == B", false),
+                ("C == A", false),
+                ("A != B", true),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_functions_compare_by_identity() {
+        assert_comparisons(
+            "fun f() {}
+            fun g() {}
+            var alias = f;
+            fun make() { fun inner() {} return inner; }
+            var first = make();
+            var second = make();",
+            &[
+                ("f == f", true),
+                ("f == alias", true),
+                ("f == g", false),
+                ("first == first", true),
+                ("first == second", false),
+                ("clock == clock", true),
+                ("clock == len", false),
+                ("clock == f", false),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_bound_methods_are_new_functions() {
+        assert_comparisons(
+            "class A { method() {} }
+            var a = A();
+            var method = a.method;",
+            &[("method == method", true), ("a.method == a.method", false)],
+        );
+    }
+
+    #[test]
+    fn test_lists_and_maps_compare_by_identity() {
+        assert_comparisons(
+            "var list = [1, 2];
+            var same = list;
+            var map = {\"a\": 1};",
+            &[
+                ("list == list", true),
+                ("list == same", true),
+                ("[1, 2] == [1, 2]", false),
+                ("map == map", true),
+                ("{\"a\": 1} == {\"a\": 1}", false),
+                ("list == map", false),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_ordering_operators() {
+        assert_comparisons(
+            "",
+            &[
+                ("1 < 2", true),
+                ("2 < 1", false),
+                ("2 < 2", false),
+                ("2 <= 2", true),
+                ("3 <= 2", false),
+                ("3 > 2", true),
+                ("2 > 2", false),
+                ("2 >= 2", true),
+                ("1 >= 2", false),
+                ("-1 < 0", true),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_ordering_operators_require_numbers() {
+        for expression in ["\"a\" < \"b\"", "1 > nil", "true <= false", "[] >= []"] {
+            assert!(
+                evaluate_after("", expression).is_err(),
+                "`{}` should be a runtime error",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_calls_with_the_wrong_number_of_arguments() {
+        let source = "fun add(a, b) { return a + b; } class Point { init(x, y) {} }";
+
+        for (expression, message) in [
+            ("add(1)", "Expected 2 arguments but got 1."),
+            ("add(1, 2, 3)", "Expected 2 arguments but got 3."),
+            ("Point()", "Expected 2 arguments but got 0."),
+            ("clock(1)", "Expected 0 arguments but got 1."),
+        ] {
+            match evaluate_after(source, expression) {
+                Err(LoxError::RuntimeError(error)) => assert_eq!(error.get_error().0, message),
+                _ => panic!("`{}` should be an arity error", expression),
+            }
+        }
+    }
+
+    #[test]
+    fn test_caught_errors_hide_where_they_were_raised() {
+        for (source, message) in [
+            (
+                "var x = 1; x.field;",
+                "Only instances and classes have properties.",
+            ),
+            ("var x = 1; x();", "Can only call functions and classes."),
+            ("missing;", "Undefined variable 'missing'."),
+            ("[1] * 2;", "Expression: '[1] * 2' does not evaluate."),
+        ] {
+            let value = evaluate_after(
+                &format!(
+                    "var message; try {{ {} }} catch (e) {{ message = e.message; }}",
+                    source
+                ),
+                "message",
+            );
+
+            match value {
+                Ok(Object::Str(caught)) => assert_eq!(caught, message),
+                _ => panic!("`{}` should be caught", source),
+            }
+        }
+    }
+
+    #[test]
+    fn test_caught_negation_of_a_non_number() {
+        let value = evaluate_after(
+            "var message; var line;\ntry { print -\"a\"; } catch (e) { message = e.message; line = e.line; }",
+            "\"${message} ${line}\"",
+        );
+
+        match value {
+            Ok(Object::Str(caught)) => assert_eq!(caught, "Operand must be a number. 2"),
+            _ => panic!("Expected the caught error's message and line"),
+        }
+    }
+
+    #[test]
+    fn test_running_code_does_not_use_up_token_ids() {
+        let source = "class A {}
+        fun f(n) { { var m = -n; } return nil; }
+        for (var i = 0; i < 5000; i = i + 1) { f(i); A(); }";
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new(source.to_string(), error_reporter);
+        let tokens = scanner.scan_tokens();
+        let statements = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        Resolver::new(interpreter.locals())
+            .resolve(&statements)
+            .unwrap();
+
+        let first = generate_id();
+        interpreter.interpret(&statements).unwrap();
+        let used = generate_id() - first;
+
+        // other tests scan in parallel, so only a count near the number of iterations means the loop took ids
+        assert!(used < 5000, "running took {} token ids", used);
+    }
+
+    // test threads have a small stack, so these tests keep the call depth low
+
+    #[test]
+    fn test_runaway_recursion_is_a_stack_overflow_error() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_max_call_depth(20);
+
+        match evaluate_in(&mut interpreter, "fun f() { return f(); }", "f()") {
+            Err(LoxError::RuntimeError(error)) => {
+                assert_eq!(error.get_error().0, "Stack overflow.");
+                assert_eq!(error.backtrace().len(), 20);
+            }
+            _ => panic!("Expected a stack overflow error"),
+        }
+        assert_eq!(interpreter.call_depth(), 0);
+    }
+
+    #[test]
+    fn test_stack_overflow_can_be_caught() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_max_call_depth(20);
+
+        let value = evaluate_in(
+            &mut interpreter,
+            "var depth = 0;
+            fun f() { depth = depth + 1; f(); }
+            var message;
+            try { f(); } catch (e) { message = e.message; }",
+            "message + \" after \" + depth",
+        );
+
+        match value {
+            Ok(Object::Str(message)) => assert_eq!(message, "Stack overflow. after 20"),
+            _ => panic!("Expected the caught error's message"),
+        }
+    }
+
+    #[test]
+    fn test_recursive_getters_and_setters_overflow() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_max_call_depth(20);
+
+        let value = evaluate_in(
+            &mut interpreter,
+            "class A { x { return this.x; } }
+            class B { set x(value) { this.x = value; } }
+            var messages = \"\";
+            try { print A().x; } catch (e) { messages = e.message; }
+            try { B().x = 1; } catch (e) { messages = messages + \" \" + e.message; }",
+            "messages",
+        );
+
+        match value {
+            Ok(Object::Str(messages)) => assert_eq!(messages, "Stack overflow. Stack overflow."),
+            _ => panic!("Expected both errors to be caught"),
+        }
+        assert_eq!(interpreter.call_depth(), 0);
+    }
+
+    #[test]
+    fn test_calls_up_to_the_limit_succeed() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_max_call_depth(20);
+
+        let value = evaluate_in(
+            &mut interpreter,
+            "fun count(n) { if (n == 1) return 1; return count(n - 1) + 1; }",
+            "count(20)",
+        );
+
+        assert!(matches!(value, Ok(Object::Num(n)) if n == 20.0));
+    }
+
+    #[test]
+    fn test_running_out_of_fuel_stops_the_script() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_fuel(Some(500));
+
+        let result = evaluate_in(
+            &mut interpreter,
+            "var caught = false;
+            try { while (true) {} } catch (e) { caught = true; } finally { caught = true; }",
+            "caught",
+        );
+
+        assert!(matches!(
+            result,
+            Err(LoxError::LimitError(LimitError::OutOfFuel(500)))
+        ));
+    }
+
+    #[test]
+    fn test_fuel_is_counted_per_run() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_fuel(Some(500));
+
+        for _ in 0..3 {
+            let result = evaluate_in(
+                &mut interpreter,
+                "var total = 0; for (var i = 0; i < 10; i = i + 1) { total = total + i; }",
+                "total",
+            );
+            assert!(matches!(result, Ok(Object::Num(n)) if n == 45.0));
+        }
+    }
+
+    #[test]
+    fn test_timeout_stops_the_script() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_timeout(Some(Duration::from_millis(50)));
+
+        let started = Instant::now();
+        let result = evaluate_in(&mut interpreter, "while (true) {}", "nil");
+
+        assert!(matches!(
+            result,
+            Err(LoxError::LimitError(LimitError::TimedOut(_)))
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
     }
 }
@@ -0,0 +1,25 @@
+//! A tree-walking interpreter for Lox.
+//!
+//! [`Lox`] runs source and keeps its globals between runs. [`Lox::eval`] returns the value of the source, or the [`Diagnostic`]s for everything that went wrong, without printing anything.
+
+mod environment;
+mod error;
+mod grammar;
+mod interpreter;
+mod lox;
+mod parser;
+mod resolver;
+mod scanner;
+
+pub use error::conversion_error::ConversionError;
+pub use error::diagnostic::{Diagnostic, DiagnosticKind};
+pub use error::limit_error::LimitError;
+pub use error::parse_error::ParseError;
+pub use error::runtime_error::{CallFrame, RuntimeError};
+pub use error::LoxError;
+pub use grammar::convert::{field, FromLox, IntoLox};
+pub use grammar::instance::LoxInstance;
+pub use grammar::native_function::{IntoNativeResult, NativeMethod, NativeResult, TypedNative};
+pub use grammar::object::Object;
+pub use interpreter::{Interpreter, DEFAULT_MAX_CALL_DEPTH};
+pub use lox::{Lox, LoxBuilder};
@@ -3,57 +3,153 @@ use std::{
     cell::RefCell,
     fs,
     io::{self},
-    process,
     rc::Rc,
+    time::Duration,
 };
 
+use crate::error::diagnostic::Diagnostic;
 use crate::error::LoxError;
+use crate::grammar::native_function::{NativeMethod, NativeResult, TypedNative};
+use crate::grammar::object::Object;
 use crate::resolver::Resolver;
 use crate::{
     error::error::ErrorReporter, interpreter::Interpreter, parser::Parser, scanner::Scanner,
 };
 
+const WELCOME_BANNER: &str = "\n\n--------------\nWelcome to Lox\n--------------\n\n\n";
+
+const MULTILINE_BANNER: &str = "\n\n---------------------------------------
+--- Welcome to Lox (multiline mode) ---
+---------------------------------------
+Ctrl-D to finish input and run the code \n\n\n";
+
+/// # Lox
+///
+/// Runs Lox source. Globals persist from one run to the next, like they do in the REPL.
 pub struct Lox {
-    error_reporter: Rc<RefCell<ErrorReporter>>,
     interpreter: Rc<RefCell<Interpreter>>,
+    had_error: bool,
+    /// Collects the scanner's errors, and reports the errors of `run_file` and the REPL.
+    error_reporter: Rc<RefCell<ErrorReporter>>,
+}
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
 impl Lox {
     pub fn new() -> Self {
         Self {
-            // use reference counter to count references for any sub impl that will need to report errors
-            error_reporter: Rc::new(RefCell::new(ErrorReporter::new())),
             interpreter: Rc::new(RefCell::new(Interpreter::new())),
+            had_error: false,
+            error_reporter: Rc::new(RefCell::new(ErrorReporter::new())),
         }
     }
 
-    pub fn error(&self, error: LoxError) {
-        match error {
-            LoxError::RuntimeError(error) => {
-                self.error_reporter.borrow_mut().report_runtime_error(error)
-            }
-            LoxError::ParseError(error) => {
-                self.error_reporter.borrow_mut().report_parse_error(error)
-            }
-            LoxError::LoxReturn(error) => println!("Lox Return {}", error),
-        }
+    /// # builder
+    /// Starts configuring a `Lox`, for example to send its output and errors to writers the host owns.
+    pub fn builder() -> LoxBuilder {
+        LoxBuilder::default()
+    }
+
+    /// # had_error
+    /// Whether any run so far has reported an error.
+    pub fn had_error(&self) -> bool {
+        self.had_error
+    }
+
+    /// # set_output
+    /// Sends the output of print statements to the writer instead of standard output. The banners and the prompt of `run_file` and the REPL go there too.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.interpreter.borrow_mut().set_output(output);
+    }
+
+    /// # set_error_output
+    /// Sends the errors of `run_file` and the REPL to the writer instead of standard error. `eval` returns its errors rather than reporting them.
+    pub fn set_error_output(&mut self, error_output: Box<dyn Write>) {
+        self.error_reporter.borrow_mut().set_output(error_output);
+    }
+
+    /// # set_max_call_depth
+    /// How deep Lox calls may nest before they fail with a catchable stack overflow error. Each call takes native stack, about 7 to 12 KB in a release build and 25 to 50 KB in a debug build. Calls also fail once the stack of the thread running Lox is nearly used up, so a limit that is too high for the thread is cut short instead of crashing the process.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.interpreter
+            .borrow_mut()
+            .set_max_call_depth(max_call_depth);
+    }
+
+    /// # set_fuel
+    /// Limits every run to the given number of steps, where a step is one statement or expression. None removes the limit.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.interpreter.borrow_mut().set_fuel(fuel);
+    }
+
+    /// # set_timeout
+    /// Limits how long every run may take. None removes the limit.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.interpreter.borrow_mut().set_timeout(timeout);
+    }
+
+    /// # define_native
+    /// Exposes a Rust closure to Lox as a global function with the given name and arity.
+    ///
+    /// The arguments are checked against the arity before the closure runs. An `Err` message becomes a runtime error at the call.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: u8,
+        function: impl Fn(&mut Interpreter, Vec<Object>) -> NativeResult + 'static,
+    ) {
+        self.interpreter
+            .borrow_mut()
+            .define_native(name, arity, function);
+    }
+
+    /// # define_typed_native
+    /// Exposes a Rust closure with typed parameters, like `|a: f64, b: f64| a + b`. Arguments are converted with `FromLox` and the result with `IntoLox`, so an argument of the wrong type is a runtime error naming the function and the argument.
+    ///
+    /// The closure can return a `Result<T, String>` to fail with its own message.
+    pub fn define_typed_native<Arguments>(
+        &mut self,
+        name: &str,
+        function: impl TypedNative<Arguments>,
+    ) {
+        self.interpreter
+            .borrow_mut()
+            .define_typed_native(name, function);
+    }
+
+    /// # define_native_class
+    /// Exposes a class implemented in Rust, such as a wrapper around a file handle. Its native `init` usually stores the Rust value in the new instance with `LoxInstance::set_payload`, and the other methods read it back with `LoxInstance::payload`.
+    ///
+    /// Scripts can subclass the class and override its methods.
+    pub fn define_native_class(&mut self, name: &str, methods: Vec<NativeMethod>) {
+        self.interpreter
+            .borrow_mut()
+            .define_native_class(name, methods);
+    }
+
+    /// # write_output
+    /// Writes text that is not the output of a print statement, like a banner, to the same writer.
+    fn write_output(&mut self, text: &str) -> io::Result<()> {
+        let mut interpreter = self.interpreter.borrow_mut();
+        let output = interpreter.output();
+
+        output.write_all(text.as_bytes())?;
+        output.flush()
     }
 
     pub fn run_file(&mut self, path: &str) -> io::Result<()> {
-        println!("\n");
-        println!("--------------");
-        println!("Welcome to Lox");
-        println!("--------------");
-        println!("\n");
-        println!("running file {} \n\n", path);
+        self.write_output(WELCOME_BANNER)?;
+        self.write_output(&format!("running file {} \n\n\n", path))?;
 
         let bytes = fs::read(path)?;
         let content = String::from_utf8_lossy(&bytes).to_string();
 
-        let _value_of_run = self.run(content);
+        self.interpreter.borrow_mut().set_script_path(path);
 
-        if self.error_reporter.borrow_mut().had_error() {
-            process::exit(65);
-        }
+        self.run(content);
 
         Ok(())
     }
@@ -62,16 +158,10 @@ impl Lox {
         let input = io::stdin();
         let mut reader = input.lock();
 
-        println!("\n");
-        println!("--------------");
-        println!("Welcome to Lox");
-        println!("--------------");
-        println!("\n");
+        self.write_output(WELCOME_BANNER)?;
 
         loop {
-            print!("> ");
-
-            io::stdout().flush().unwrap();
+            self.write_output("> ")?;
 
             let mut line = String::new();
             match reader.read_line(&mut line) {
@@ -79,10 +169,7 @@ impl Lox {
                     self.run(line);
                 }
                 Err(error) => {
-                    self.error_reporter.borrow_mut().set_error(false);
-                    self.error_reporter
-                        .borrow_mut()
-                        .report_error_message(0, &error.to_string());
+                    self.report(&Diagnostic::scan(0, &error.to_string()));
                     break;
                 }
                 Ok(_) => break, // EOF (Ctrl+D on Unix, Ctrl+Z on Windows)
@@ -93,10 +180,7 @@ impl Lox {
     }
 
     pub fn run_prompt_multiline(&mut self) -> io::Result<()> {
-        println!("\n\n---------------------------------------");
-        println!("--- Welcome to Lox (multiline mode) ---");
-        println!("---------------------------------------");
-        println!("Ctrl-D to finish input and run the code \n\n");
+        self.write_output(MULTILINE_BANNER)?;
 
         let mut lines = String::new();
         let stdin = io::stdin();
@@ -104,7 +188,7 @@ impl Lox {
         for line in stdin.lock().lines() {
             let line = line?;
             if line.trim() == "RUN" {
-                println!("\n\nRunning code... \n\n");
+                self.write_output("\n\nRunning code... \n\n\n")?;
                 break;
             }
             lines.push_str(&line);
@@ -116,26 +200,583 @@ impl Lox {
         Ok(())
     }
 
-    fn run(&self, source: String) {
-        let mut scanner = Scanner::new(source, Rc::clone(&self.error_reporter));
+    /// # eval
+    /// Runs the source and returns the value of its last statement if that is an expression statement, or nil.
+    ///
+    /// Nothing is printed for errors. They are returned instead, and all of them are returned when the source does not scan or parse.
+    pub fn eval(&mut self, source: &str) -> Result<Object, Vec<Diagnostic>> {
+        let mut scanner = Scanner::new(source.to_string(), Rc::clone(&self.error_reporter));
         let tokens = scanner.scan_tokens();
 
         let mut parser = Parser::new(tokens);
+        let statements = parser.parse();
+
+        let mut diagnostics = self.error_reporter.borrow_mut().take_diagnostics();
+        let statements = match statements {
+            Ok(statements) if diagnostics.is_empty() => statements,
+            Ok(_) => return Err(diagnostics),
+            Err(errors) => {
+                diagnostics.extend(
+                    errors
+                        .into_iter()
+                        .map(|error| Diagnostic::from(LoxError::ParseError(error))),
+                );
+                return Err(diagnostics);
+            }
+        };
 
-        let mut statements = parser.parse();
+        let mut resolver = Resolver::new(self.interpreter.borrow().locals());
+        resolver
+            .resolve(&statements)
+            .map_err(|error| vec![Diagnostic::from(error)])?;
 
-        if let Ok(stmts) = &mut statements {
-            let mut resolver = Resolver::new(self.interpreter.clone());
+        self.interpreter
+            .borrow_mut()
+            .interpret(&statements)
+            .map_err(|error| vec![Diagnostic::from(error)])
+    }
 
-            if let Err(error) = resolver.resolve(stmts) {
-                return self.error(error);
-            };
+    /// # global
+    /// The value of a global the source defined, like a function to call later with call_function.
+    pub fn global(&self, name: &str) -> Option<Object> {
+        self.interpreter.borrow().globals().get(name).cloned()
+    }
 
-            if let Err(error) = self.interpreter.borrow_mut().interpret(stmts) {
-                return self.error(error);
+    /// # call_function
+    /// Calls a global function or class from Rust and returns its result. The call runs with the same limits as a call to eval.
+    ///
+    /// The diagnostic is boxed, because it is large and most calls succeed.
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        arguments: Vec<Object>,
+    ) -> Result<Object, Box<Diagnostic>> {
+        self.interpreter
+            .borrow_mut()
+            .call_function(name, arguments)
+            .map_err(|error| Box::new(Diagnostic::from(error)))
+    }
+
+    /// # call_method
+    /// Calls a method of an instance from Rust, with `this` bound to the instance.
+    pub fn call_method(
+        &mut self,
+        instance: &Object,
+        name: &str,
+        arguments: Vec<Object>,
+    ) -> Result<Object, Box<Diagnostic>> {
+        self.interpreter
+            .borrow_mut()
+            .call_method(instance, name, arguments)
+            .map_err(|error| Box::new(Diagnostic::from(error)))
+    }
+
+    /// # run
+    /// Runs the source and prints its errors.
+    fn run(&mut self, source: String) {
+        if let Err(diagnostics) = self.eval(&source) {
+            for diagnostic in &diagnostics {
+                self.report(diagnostic);
             }
-        } else if let Err(error) = statements {
-            return self.error(LoxError::ParseError(error));
+            self.had_error = true;
+        }
+    }
+
+    fn report(&mut self, diagnostic: &Diagnostic) {
+        self.error_reporter.borrow_mut().report(diagnostic);
+    }
+}
+
+/// # LoxBuilder
+/// Configures a `Lox` before it runs anything, as an alternative to calling its setters one by one. Anything left unset keeps the default of `Lox::new`.
+#[derive(Default)]
+pub struct LoxBuilder {
+    output: Option<Box<dyn Write>>,
+    error_output: Option<Box<dyn Write>>,
+    max_call_depth: Option<usize>,
+    fuel: Option<u64>,
+    timeout: Option<Duration>,
+}
+
+impl LoxBuilder {
+    /// Where print statements, banners and the prompt are written.
+    pub fn output(mut self, output: Box<dyn Write>) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Where the errors of `run_file` and the REPL are written.
+    pub fn error_output(mut self, error_output: Box<dyn Write>) -> Self {
+        self.error_output = Some(error_output);
+        self
+    }
+
+    /// How deep Lox calls may nest, see `Lox::set_max_call_depth` for the stack each call needs.
+    pub fn max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = Some(max_call_depth);
+        self
+    }
+
+    pub fn fuel(mut self, fuel: Option<u64>) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn build(self) -> Lox {
+        let mut lox = Lox::new();
+
+        if let Some(output) = self.output {
+            lox.set_output(output);
+        }
+        if let Some(error_output) = self.error_output {
+            lox.set_error_output(error_output);
+        }
+        if let Some(max_call_depth) = self.max_call_depth {
+            lox.set_max_call_depth(max_call_depth);
+        }
+        lox.set_fuel(self.fuel);
+        lox.set_timeout(self.timeout);
+
+        lox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::diagnostic::DiagnosticKind;
+    use crate::grammar::convert::{FromLox, IntoLox};
+
+    #[test]
+    fn test_eval_returns_the_value_of_the_last_expression() {
+        let mut lox = Lox::new();
+
+        match lox.eval("var a = 20; a + 22;") {
+            Ok(Object::Num(n)) => assert_eq!(n, 42.0),
+            _ => panic!("Expected 42"),
+        }
+        assert!(matches!(lox.eval("var b = 1;"), Ok(Object::Nil)));
+    }
+
+    #[test]
+    fn test_eval_keeps_globals_between_runs() {
+        let mut lox = Lox::new();
+
+        lox.eval("var greeting = \"hi\"; fun shout(s) { return s + \"!\"; }")
+            .unwrap();
+
+        match lox.eval("shout(greeting);") {
+            Ok(Object::Str(s)) => assert_eq!(s, "hi!"),
+            _ => panic!("Expected a string"),
+        }
+    }
+
+    #[test]
+    fn test_eval_returns_every_syntax_error() {
+        let mut lox = Lox::new();
+
+        let diagnostics = lox.eval("var a = @;\nprint ;\nvar = 1;").unwrap_err();
+        let found: Vec<(DiagnosticKind, usize)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.kind, diagnostic.line))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticKind::Scan, 1),
+                (DiagnosticKind::Parse, 1),
+                (DiagnosticKind::Parse, 2),
+                (DiagnosticKind::Parse, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_eval_does_not_run_source_with_scan_errors() {
+        let mut lox = Lox::new();
+
+        assert!(lox.eval("var ran = true; @").is_err());
+        assert!(lox.eval("ran;").is_err());
+    }
+
+    #[test]
+    fn test_runtime_error_diagnostic() {
+        let mut lox = Lox::new();
+
+        let diagnostics = lox
+            .eval("fun half(n) {\n  return n / \"2\";\n}\nhalf(4);")
+            .unwrap_err();
+
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.kind, DiagnosticKind::Runtime);
+        assert_eq!(diagnostic.line, 2);
+        assert_eq!(diagnostic.column, Some(12));
+        assert_eq!(diagnostic.backtrace.len(), 1);
+        assert_eq!(diagnostic.backtrace[0].function, "half");
+        assert_eq!(
+            diagnostic.snippet().unwrap().lines().nth(2),
+            Some("2 |   return n / \"2\";")
+        );
+    }
+
+    #[test]
+    fn test_define_native() {
+        let mut lox = Lox::new();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+
+        let seen = Rc::clone(&calls);
+        lox.define_native("record", 1, move |_, arguments| {
+            seen.borrow_mut().push(arguments[0].to_string());
+            Ok(Object::Num(seen.borrow().len() as f64))
+        });
+
+        match lox.eval("record(\"a\"); record(1 + 2);") {
+            Ok(Object::Num(n)) => assert_eq!(n, 2.0),
+            _ => panic!("Expected 2"),
+        }
+        assert_eq!(*calls.borrow(), vec!["a".to_string(), "3".to_string()]);
+
+        let native = lox.eval("record;").unwrap();
+        assert_eq!(native.to_string(), "<native fn record>");
+    }
+
+    #[test]
+    fn test_define_native_errors() {
+        let mut lox = Lox::new();
+        lox.define_native("fail", 0, |_, _| Err("Host failure.".to_string()));
+
+        let diagnostics = lox.eval("fail(1);").unwrap_err();
+        assert_eq!(diagnostics[0].message, "Expected 0 arguments but got 1.");
+
+        let diagnostics = lox.eval("\n\nfail();").unwrap_err();
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::Runtime);
+        assert_eq!(diagnostics[0].message, "Host failure.");
+        assert_eq!(diagnostics[0].line, 3);
+    }
+
+    #[test]
+    fn test_define_typed_native() {
+        let mut lox = Lox::new();
+        lox.define_typed_native("hypot", |a: f64, b: f64| (a * a + b * b).sqrt());
+        lox.define_typed_native("repeat", |s: String, times: usize| s.repeat(times));
+        lox.define_typed_native("sum", |numbers: Vec<f64>| numbers.iter().sum::<f64>());
+        lox.define_typed_native("first", |words: Vec<String>| {
+            words.into_iter().next().ok_or("No words.".to_string())
+        });
+
+        match lox.eval("hypot(3, 4) + sum([1, 2, 3]);") {
+            Ok(Object::Num(n)) => assert_eq!(n, 11.0),
+            _ => panic!("Expected 11"),
         }
+        match lox.eval("repeat(first([\"ab\", \"c\"]), 2);") {
+            Ok(Object::Str(s)) => assert_eq!(s, "abab"),
+            _ => panic!("Expected a string"),
+        }
+
+        let diagnostics = lox.eval("hypot(3, \"4\");").unwrap_err();
+        assert_eq!(
+            diagnostics[0].message,
+            "Bad argument 2 to hypot(). Expected a number but got string."
+        );
+
+        let diagnostics = lox.eval("repeat(\"a\", 1.5);").unwrap_err();
+        assert_eq!(
+            diagnostics[0].message,
+            "Bad argument 2 to repeat(). Expected an integer but got 1.5."
+        );
+
+        let diagnostics = lox.eval("first([]);").unwrap_err();
+        assert_eq!(diagnostics[0].message, "No words.");
+    }
+
+    /// A native class whose instances count up from a starting number kept in Rust.
+    fn define_counter(lox: &mut Lox) {
+        lox.define_native_class(
+            "Counter",
+            vec![
+                NativeMethod::new("init", 1, |_, this, arguments| {
+                    this.set_payload(f64::from_lox(&arguments[0])?);
+                    Ok(Object::Nil)
+                }),
+                NativeMethod::new("increment", 0, |_, this, _| {
+                    let mut count = this
+                        .payload::<f64>()
+                        .ok_or("Counter was not initialized.".to_string())?;
+                    *count += 1.0;
+                    Ok(Object::Num(*count))
+                }),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_define_native_class() {
+        let mut lox = Lox::new();
+        define_counter(&mut lox);
+
+        let source =
+            "var counter = Counter(10); var same = counter; counter.increment(); same.increment();";
+        match lox.eval(source) {
+            Ok(Object::Num(n)) => assert_eq!(n, 12.0),
+            _ => panic!("Expected 12"),
+        }
+
+        let diagnostics = lox.eval("Counter(\"ten\");").unwrap_err();
+        assert_eq!(diagnostics[0].message, "Expected a number but got string.");
+    }
+
+    #[test]
+    fn test_subclass_native_class() {
+        let mut lox = Lox::new();
+        define_counter(&mut lox);
+
+        let source = "
+class Double < Counter {
+  init() { super.init(100); }
+  increment() { super.increment(); return super.increment(); }
+}
+class Plain < Counter {}
+Double().increment() + Plain(1).increment();";
+        match lox.eval(source) {
+            Ok(Object::Num(n)) => assert_eq!(n, 104.0),
+            _ => panic!("Expected 104"),
+        }
+
+        let diagnostics = lox
+            .eval("class Broken < Counter { init() {} } Broken().increment();")
+            .unwrap_err();
+        assert_eq!(diagnostics[0].message, "Counter was not initialized.");
+    }
+
+    /// A writer whose contents the test can still read after handing it to Lox.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_print_output_can_be_captured() {
+        let mut lox = Lox::new();
+        let output = SharedBuffer::default();
+        lox.set_output(Box::new(output.clone()));
+
+        lox.eval("print 1 + 2; print \"lox\"; print [1, nil];")
+            .unwrap();
+
+        assert_eq!(output.contents(), "3\nlox\n[1, <LoxNil>]\n");
+    }
+
+    #[test]
+    fn test_run_file_writes_to_the_output() {
+        let path = std::env::temp_dir().join("lox_run_file_writes_to_the_output.lox");
+        fs::write(&path, "print 1;").unwrap();
+
+        let mut lox = Lox::new();
+        let output = SharedBuffer::default();
+        lox.set_output(Box::new(output.clone()));
+        lox.run_file(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let contents = output.contents();
+        assert!(contents.starts_with(WELCOME_BANNER));
+        assert!(contents.ends_with(" \n\n\n1\n"));
+    }
+
+    /// A writer that refuses every write.
+    struct BrokenPipe;
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_print_failure_points_at_the_print() {
+        let mut lox = Lox::new();
+        lox.set_output(Box::new(BrokenPipe));
+
+        let diagnostics = lox.eval("var a = 1;\n\nprint a;").unwrap_err();
+        assert!(diagnostics[0].message.starts_with("Could not print: "));
+        assert_eq!(diagnostics[0].line, 3);
+    }
+
+    #[test]
+    fn test_builder_configures_the_sinks() {
+        let output = SharedBuffer::default();
+        let errors = SharedBuffer::default();
+        let mut lox = Lox::builder()
+            .output(Box::new(output.clone()))
+            .error_output(Box::new(errors.clone()))
+            .build();
+
+        lox.run("print \"out\";\nprint -nil;\nvar s = \"open;".to_string());
+        lox.run("print -nil;".to_string());
+
+        assert_eq!(output.contents(), "");
+        assert!(errors
+            .contents()
+            .starts_with("[line 3] Error: Unterminated string."));
+        assert!(errors
+            .contents()
+            .contains("[line 1]: Operand must be a number."));
+    }
+
+    #[test]
+    fn test_errors_can_be_captured() {
+        let mut lox = Lox::new();
+        let output = SharedBuffer::default();
+        let errors = SharedBuffer::default();
+        lox.set_output(Box::new(output.clone()));
+        lox.set_error_output(Box::new(errors.clone()));
+
+        lox.run("print \"before\";\nprint nil * 2;".to_string());
+
+        assert!(lox.had_error());
+        assert_eq!(output.contents(), "before\n");
+        assert!(errors.contents().starts_with(
+            "Runtime Error - [line 2]: Expression: '<LoxNil> * 2' does not evaluate."
+        ));
+    }
+
+    #[test]
+    fn test_call_function_from_rust() {
+        let mut lox = Lox::new();
+        lox.eval(
+            "var clicks = 0;
+fun on_click(times) { clicks = clicks + times; return clicks; }
+class Greeter { init(name) { this.name = name; } greet(greeting) { return greeting + \", \" + this.name; } }",
+        )
+        .unwrap();
+
+        lox.call_function("on_click", vec![Object::Num(2.0)])
+            .unwrap();
+        match lox.call_function("on_click", vec![Object::Num(3.0)]) {
+            Ok(Object::Num(n)) => assert_eq!(n, 5.0),
+            _ => panic!("Expected 5"),
+        }
+        assert!(matches!(lox.global("clicks"), Some(Object::Num(n)) if n == 5.0));
+
+        let greeter = lox
+            .call_function("Greeter", vec!["Ada".into_lox()])
+            .unwrap();
+        match lox.call_method(&greeter, "greet", vec!["Hello".into_lox()]) {
+            Ok(Object::Str(s)) => assert_eq!(s, "Hello, Ada"),
+            _ => panic!("Expected a greeting"),
+        }
+    }
+
+    #[test]
+    fn test_host_converts_a_result() -> Result<(), Box<dyn std::error::Error>> {
+        let mut lox = Lox::new();
+        lox.eval("fun pair() { return [1, \"two\"]; }").unwrap();
+
+        let pair = lox.call_function("pair", vec![])?;
+        let error = Vec::<f64>::from_lox(&pair).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "List element 1: Expected a number but got string."
+        );
+
+        let first = f64::from_lox(&Vec::<Object>::from_lox(&pair)?[0])?;
+        assert_eq!(first, 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_call_function_errors() {
+        let mut lox = Lox::new();
+        lox.eval("fun fail(n) { return n * nil; } var answer = 42;")
+            .unwrap();
+
+        let error = lox.call_function("missing", vec![]).unwrap_err();
+        assert_eq!(error.message, "Undefined variable 'missing'.");
+
+        let error = lox.call_function("fail", vec![]).unwrap_err();
+        assert_eq!(error.message, "Expected 1 arguments but got 0.");
+
+        let error = lox
+            .call_function("fail", vec![Object::Num(1.0)])
+            .unwrap_err();
+        assert_eq!(error.kind, DiagnosticKind::Runtime);
+        assert_eq!(error.backtrace[0].function, "fail");
+
+        let error = lox.call_function("answer", vec![]).unwrap_err();
+        assert!(error
+            .message
+            .starts_with("Can only call functions and classes."));
+
+        let error = lox
+            .call_method(&Object::Num(1.0), "abs", vec![])
+            .unwrap_err();
+        assert_eq!(error.message, "Only instances have methods, not number.");
+    }
+
+    #[test]
+    fn test_native_calls_back_into_lox() {
+        let mut lox = Lox::new();
+        lox.set_fuel(Some(1000));
+        lox.define_native("each", 2, |interpreter, arguments| {
+            let name = String::from_lox(&arguments[1])?;
+            for element in Vec::<Object>::from_lox(&arguments[0])? {
+                interpreter
+                    .call_function(&name, vec![element])
+                    .map_err(|error| Diagnostic::from(error).message)?;
+            }
+            Ok(Object::Nil)
+        });
+
+        lox.eval("var total = 0; fun add(n) { total = total + n; } each([1, 2, 3], \"add\");")
+            .unwrap();
+        assert!(matches!(lox.global("total"), Some(Object::Num(n)) if n == 6.0));
+
+        // the callbacks use up the fuel of the run that called the native
+        let error = lox
+            .eval("fun spin(n) { while (true) {} } each([1], \"spin\");")
+            .unwrap_err();
+        assert!(error[0].message.contains("Out of fuel"));
+    }
+
+    #[test]
+    fn test_default_call_depth_fits_a_default_thread() {
+        // a spawned thread gets the default stack, much smaller than the CLI's
+        let errors = std::thread::spawn(|| {
+            let mut lox = Lox::new();
+            let runaway = lox.eval("fun f(n) { return f(n + 1); } f(0);").unwrap_err();
+            let nested = lox
+                .eval("fun g(n) { if (n == 0) return 0; return [ {\"a\": g(n - 1)} ][0][\"a\"] + 1; } g(2000);")
+                .unwrap_err();
+            (runaway, nested)
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(errors.0[0].message, "Stack overflow.");
+        assert_eq!(errors.1[0].message, "Stack overflow.");
     }
 }
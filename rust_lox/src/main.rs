@@ -1,31 +1,69 @@
-use std::env;
+use std::{env, process, thread, time::Duration};
 
 use getopts::Options;
 
-use crate::lox::Lox;
+use rlox_interpreter::Lox;
 
-mod environment;
-mod error;
-mod grammar;
-mod interpreter;
-mod lox;
-mod parser;
-mod resolver;
-mod scanner;
+/// The interpreter walks the syntax tree recursively, so every Lox call takes a good deal of native stack. The interpreter runs on a thread with a stack this big so the default call depth limit is reached well before the stack runs low, in any build. A higher `--max-call-depth` than the stack holds ends in a stack overflow error, not a crash.
+const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
-    let mut lox = Lox::new();
+    let interpreter = thread::Builder::new()
+        .name("lox".to_string())
+        .stack_size(INTERPRETER_STACK_SIZE)
+        .spawn(run)
+        .expect("Could not start the interpreter thread.");
 
+    // the panic has already been printed by the interpreter thread
+    if interpreter.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run() {
     let args: Vec<String> = env::args().collect();
 
     let mut opts = Options::new();
     opts.optflag("", "multiline", "enable multiline mode");
+    opts.optopt(
+        "",
+        "max-call-depth",
+        "how deep Lox calls may nest before a stack overflow error",
+        "DEPTH",
+    );
+    opts.optopt(
+        "",
+        "fuel",
+        "how many statements and expressions a run may evaluate",
+        "STEPS",
+    );
+    opts.optopt("", "timeout", "how many milliseconds a run may take", "MS");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(f) => panic!("{}", f.to_string()),
     };
 
+    let mut builder = Lox::builder();
+
+    match matches.opt_get::<usize>("max-call-depth") {
+        Ok(Some(max_call_depth)) => builder = builder.max_call_depth(max_call_depth),
+        Ok(None) => (),
+        Err(f) => panic!("--max-call-depth: {}", f),
+    }
+
+    match matches.opt_get::<u64>("fuel") {
+        Ok(fuel) => builder = builder.fuel(fuel),
+        Err(f) => panic!("--fuel: {}", f),
+    }
+
+    match matches.opt_get::<u64>("timeout") {
+        Ok(timeout) => builder = builder.timeout(timeout.map(Duration::from_millis)),
+        Err(f) => panic!("--timeout: {}", f),
+    }
+
+    let mut lox = builder.build();
+
     if matches.opt_present("multiline") {
         let prompt = lox.run_prompt_multiline(); // assuming you have a run_prompt_multiline method
 
@@ -33,8 +71,12 @@ fn main() {
             Ok(_value) => (),
             Err(_e) => (),
         }
-    } else if args.len() == 2 {
-        lox.run_file(&args[1]).unwrap();
+    } else if matches.free.len() == 1 {
+        lox.run_file(&matches.free[0]).unwrap();
+
+        if lox.had_error() {
+            process::exit(65);
+        }
     } else {
         let prompt = lox.run_prompt();
 
@@ -3,7 +3,9 @@ use std::vec;
 use crate::environment::generate_id;
 use crate::error::parse_error::ParseError;
 use crate::grammar::object::Object;
-use crate::grammar::stmt::{BlockStmt, ClassStmt, FunStmt, FunType, Stmt};
+use crate::grammar::stmt::{
+    BlockStmt, CatchClause, ClassStmt, FunStmt, FunType, ImportStmt, Stmt, TryStmt,
+};
 use crate::grammar::token::{Token, TokenType};
 
 use crate::grammar::expr::{Expr, Variable};
@@ -15,6 +17,10 @@ pub struct Parser<'a> {
     current: usize,
     tokens: &'a Vec<Token>,
     empty_token: Token,
+    /// Errors found so far. The parser records an error and skips to the next statement, so one run reports every error it can.
+    errors: Vec<ParseError>,
+    /// How many blocks the parser is inside. Recovery stops at a `}` only inside a block, where `block()` consumes it.
+    block_depth: usize,
 }
 
 impl<'a> Parser<'a> {
@@ -22,6 +28,7 @@ impl<'a> Parser<'a> {
         Self {
             current: 0,
             tokens,
+            block_depth: 0,
             empty_token: Token::new(
                 TokenType::Nil,
                 "".to_string(),
@@ -29,6 +36,7 @@ impl<'a> Parser<'a> {
                 0,
                 generate_id(),
             ),
+            errors: Vec::new(),
         }
     }
 
@@ -39,14 +47,22 @@ impl<'a> Parser<'a> {
     // pub fn parse(&mut self) -> Result<Vec<Expr>, ParseError> {
     //     return self.expressions();
     // }
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
+    /// Returns every parse error in source order if any declaration failed to parse.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
         let mut statements = Vec::new();
 
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => self.errors.push(e),
+            }
         }
 
-        Ok(statements)
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 
     /// # expressions
@@ -86,30 +102,26 @@ impl<'a> Parser<'a> {
     /// # declaration
     /// Called repeatedly when parsing statments in either block or script mode
     /// This is the where the application should synchronize when the parser panics.
+    ///
+    /// A declaration that fails to parse leaves the parser at the start of the next statement, and the caller records the error.
     fn declaration(&mut self) -> Result<Stmt, ParseError> {
-        if self.match_symbol(&[TokenType::Class]) {
-            return self.class_declaration();
-        }
-        if self.match_symbol(&[TokenType::Fun]) {
-            return self.function("function");
-        }
-        if self.match_symbol(&[TokenType::Var]) {
-            match self.var_declaration() {
-                Ok(stmt) => Ok(stmt),
-                Err(e) => {
-                    self.synchronize();
-                    Err(e)
-                }
-            }
+        let declaration = if self.match_symbol(&[TokenType::Class]) {
+            self.class_declaration()
+        } else if self.check(&TokenType::Fun) && !self.check_next(&TokenType::LeftParen) {
+            // `fun (` starts an anonymous function, which is parsed as an expression statement
+            self.advance();
+            self.function("function")
+        } else if self.match_symbol(&[TokenType::Var]) {
+            self.var_declaration()
         } else {
-            match self.statement() {
-                Ok(stmt) => Ok(stmt),
-                Err(e) => {
-                    self.synchronize();
-                    Err(e)
-                }
-            }
+            self.statement()
+        };
+
+        if declaration.is_err() {
+            self.synchronize();
         }
+
+        declaration
     }
 
     fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
@@ -128,10 +140,27 @@ impl<'a> Parser<'a> {
         self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
 
         let mut methods: Vec<FunStmt> = vec![];
+        let mut class_methods: Vec<FunStmt> = vec![];
 
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            // a `class` prefix marks a static method
+            let is_class_method = self.match_symbol(&[TokenType::Class]);
+
+            // `set` is only a keyword when it is followed by the property name, so methods can still be called set
+            let is_setter = !is_class_method && self.check_setter();
+            if is_setter {
+                self.advance();
+            }
+
             match self.function("method") {
                 Ok(method) => match method {
+                    Stmt::Function(fun_stmt) if is_class_method => {
+                        class_methods.push(fun_stmt);
+                    }
+                    Stmt::Function(mut fun_stmt) if is_setter => {
+                        fun_stmt.kind = FunType::Setter;
+                        methods.push(fun_stmt);
+                    }
                     Stmt::Function(fun_stmt) => {
                         methods.push(fun_stmt);
                     }
@@ -148,28 +177,44 @@ impl<'a> Parser<'a> {
 
         self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
 
-        return Ok(Stmt::Class(ClassStmt {
+        return Ok(Stmt::Class(Box::new(ClassStmt {
             name,
             superclass,
             methods,
-        }));
+            class_methods,
+        })));
     }
 
     /// # statement
     /// "A program is a list of statements, and we parse one of those statements using this method"
     fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_symbol(&[TokenType::Break]) {
+            return self.break_statement();
+        }
+        if self.match_symbol(&[TokenType::Continue]) {
+            return self.continue_statement();
+        }
         if self.match_symbol(&[TokenType::For]) {
             return self.for_statement();
         }
         if self.match_symbol(&[TokenType::If]) {
             return self.if_statement();
         }
+        if self.match_symbol(&[TokenType::Import]) {
+            return self.import_statement();
+        }
         if self.match_symbol(&[TokenType::Print]) {
             return self.print_statement();
         }
         if self.match_symbol(&[TokenType::Return]) {
             return self.return_statement();
         }
+        if self.match_symbol(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+        if self.match_symbol(&[TokenType::Try]) {
+            return self.try_statement();
+        }
         if self.match_symbol(&[TokenType::While]) {
             return self.while_statement();
         }
@@ -212,26 +257,7 @@ impl<'a> Parser<'a> {
 
         self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
 
-        let mut body = self.statement();
-
-        if let Some(increment) = increment {
-            let mut body_statements = vec![];
-            if let Ok(body) = body {
-                body_statements = match body {
-                    Stmt::Block(block) => block.statements,
-                    _ => vec![body],
-                };
-            }
-            body = Ok(Stmt::Block(BlockStmt {
-                statements: {
-                    let mut v = body_statements;
-                    v.push(Stmt::Expression {
-                        expression: Box::new(increment),
-                    });
-                    v
-                },
-            }))
-        }
+        let body = self.statement()?;
 
         if let None = condition {
             condition = Some(Expr::Literal {
@@ -239,18 +265,20 @@ impl<'a> Parser<'a> {
             })
         }
 
-        body = Ok(Stmt::While {
+        // the increment is handed to the while statement rather than appended to the body, so that a `continue` in the body still runs it
+        let mut body = Stmt::While {
             condition: Box::new(condition.unwrap()),
-            body: Box::new(body.unwrap()),
-        });
+            body: Box::new(body),
+            increment: increment.map(Box::new),
+        };
 
         if let Some(initializer) = initializer {
-            body = Ok(Stmt::Block(BlockStmt {
-                statements: vec![initializer, body.unwrap()],
-            }))
+            body = Stmt::Block(BlockStmt {
+                statements: vec![initializer, body],
+            })
         }
 
-        return body;
+        Ok(body)
     }
 
     /// # if_statement
@@ -292,17 +320,40 @@ impl<'a> Parser<'a> {
         Ok(Stmt::While {
             condition: Box::new(condition),
             body,
+            increment: None,
         })
     }
 
+    /// # break_statement
+    /// parse a break statement
+    fn break_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+
+        self.consume(TokenType::Semicolon, "Expect ';' after 'break'.")?;
+
+        Ok(Stmt::Break { keyword })
+    }
+
+    /// # continue_statement
+    /// parse a continue statement
+    fn continue_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+
+        self.consume(TokenType::Semicolon, "Expect ';' after 'continue'.")?;
+
+        Ok(Stmt::Continue { keyword })
+    }
+
     /// # print_statement
     /// parse a print statement
     fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
         let value = self.expression()?;
 
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
 
         Ok(Stmt::Print {
+            keyword,
             expression: Box::new(value),
         })
     }
@@ -327,6 +378,103 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// # throw_statement
+    /// parse a throw statement
+    fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+        let value = self.expression()?;
+
+        self.consume(TokenType::Semicolon, "Expect ';' after thrown value.")?;
+
+        Ok(Stmt::Throw {
+            keyword,
+            value: Box::new(value),
+        })
+    }
+
+    /// # import_statement
+    /// parse an import statement. `as` is not a keyword, so it is matched as an identifier with that lexeme.
+    fn import_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+        let path = self
+            .consume(TokenType::String, "Expect module path after 'import'.")?
+            .clone();
+
+        let mut alias = None;
+        let mut names = Vec::new();
+
+        if self.match_contextual("as") {
+            alias = Some(
+                self.consume(TokenType::Identifier, "Expect name after 'as'.")?
+                    .clone(),
+            );
+        } else if self.match_symbol(&[TokenType::For]) {
+            loop {
+                names.push(
+                    self.consume(TokenType::Identifier, "Expect name to import.")?
+                        .clone(),
+                );
+                if !self.match_symbol(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(TokenType::Semicolon, "Expect ';' after import.")?;
+
+        Ok(Stmt::Import(Box::new(ImportStmt {
+            keyword,
+            path,
+            alias,
+            names,
+        })))
+    }
+
+    /// # try_statement
+    /// parse a try statement with a catch clause, a finally clause or both
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+
+        let catch = if self.match_symbol(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self
+                .consume(TokenType::Identifier, "Expect caught variable name.")?
+                .clone();
+            self.consume(TokenType::RightParen, "Expect ')' after caught variable.")?;
+            self.consume(TokenType::LeftBrace, "Expect '{' before catch body.")?;
+
+            Some(CatchClause {
+                name,
+                body: self.block()?,
+            })
+        } else {
+            None
+        };
+
+        let finally = if self.match_symbol(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(ParseError::new(
+                "Expect 'catch' or 'finally' after try block.",
+                &keyword,
+            ));
+        }
+
+        Ok(Stmt::Try(TryStmt {
+            body,
+            catch,
+            finally,
+        }))
+    }
+
     /// # var_declaration
     /// parse a variable declaration
     fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
@@ -380,10 +528,7 @@ impl<'a> Parser<'a> {
             name,
             params: vec![],
             kind: FunType::Getter,
-            body: self
-                .block()
-                .unwrap_or_else(|_error| BlockStmt { statements: vec![] })
-                .statements,
+            body: self.block()?.statements,
         }))
     }
 
@@ -406,6 +551,43 @@ impl<'a> Parser<'a> {
             &format!("Parser::Function() -- Expect '(' after {} name.", kind),
         )?;
 
+        let parameters = self.parameters(kind)?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+
+        Ok(Stmt::Function(FunStmt {
+            name,
+            params: parameters,
+            kind: FunType::Function,
+            body: self.block()?.statements,
+        }))
+    }
+
+    /// # lambda
+    /// parse an anonymous function expression. The `fun` keyword has already been consumed and stands in for the name.
+    fn lambda(&mut self) -> Result<Expr, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+
+        self.consume(TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+
+        let parameters = self.parameters("lambda")?;
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before lambda body.")?;
+
+        Ok(Expr::Lambda(FunStmt {
+            name: keyword,
+            params: parameters,
+            kind: FunType::Lambda,
+            body: self.block()?.statements,
+        }))
+    }
+
+    /// # parameters
+    /// parse a parameter list up to and including the closing paren
+    fn parameters(&mut self, kind: &str) -> Result<Vec<Token>, ParseError> {
         let mut parameters: Vec<Token> = vec![];
 
         if !self.check(&TokenType::RightParen) {
@@ -432,31 +614,22 @@ impl<'a> Parser<'a> {
             &format!("Expect ')' after {} parameters.", kind),
         )?;
 
-        self.consume(
-            TokenType::LeftBrace,
-            &format!("Expect '{{' before {} body.", kind),
-        )?;
-
-        Ok(Stmt::Function(FunStmt {
-            name,
-            params: parameters,
-            kind: FunType::Function,
-            body: self
-                .block()
-                .unwrap_or_else(|_error| BlockStmt { statements: vec![] })
-                .statements,
-        }))
+        Ok(parameters)
     }
 
+    /// # block
+    /// Errors in the block's statements are recorded and parsing goes on inside the block. Only a missing closing brace fails the block itself.
     fn block(&mut self) -> Result<BlockStmt, ParseError> {
         let mut statements = Vec::new();
 
+        self.block_depth += 1;
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
             match self.declaration() {
                 Ok(stmt) => statements.push(stmt),
-                Err(e) => return Err(e),
+                Err(e) => self.errors.push(e),
             }
         }
+        self.block_depth -= 1;
 
         self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
 
@@ -488,6 +661,18 @@ impl<'a> Parser<'a> {
                         value: Box::new(value),
                     });
                 }
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                } => {
+                    return Ok(Expr::IndexSet {
+                        object,
+                        bracket,
+                        index,
+                        value: Box::new(value),
+                    });
+                }
                 _ => {
                     return Err(ParseError::new(
                         &"Invalid assignment target.".to_string(),
@@ -593,6 +778,44 @@ impl<'a> Parser<'a> {
         self.previous()
     }
 
+    /// # check_setter
+    ///
+    /// Returns true if the next tokens are a `set` followed by a property name, which starts a setter declaration.
+    ///
+    fn check_setter(&self) -> bool {
+        match self.peek() {
+            Some(token) if token.token_type == TokenType::Identifier && token.lexeme == "set" => {
+                self.check_next(&TokenType::Identifier)
+            }
+            _ => false,
+        }
+    }
+
+    /// # match_contextual
+    ///
+    /// Consumes the next token if it is an identifier spelled like the given contextual keyword.
+    ///
+    fn match_contextual(&mut self, lexeme: &str) -> bool {
+        match self.peek() {
+            Some(token) if token.token_type == TokenType::Identifier && token.lexeme == lexeme => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// # check_next
+    ///
+    /// Returns true if the token after the next one is of the given type.
+    ///
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == *token_type,
+            None => false,
+        }
+    }
+
     fn is_at_end(&self) -> bool {
         match self.peek() {
             Some(token) => token.token_type == TokenType::Eof,
@@ -739,6 +962,16 @@ impl<'a> Parser<'a> {
                     object: Box::new(expr),
                     name: name.clone(),
                 };
+            } else if self.match_symbol(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self
+                    .consume(TokenType::RightBracket, "Expect ']' after index.")?
+                    .clone();
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
             } else {
                 break;
             }
@@ -754,9 +987,7 @@ impl<'a> Parser<'a> {
 
         if !self.check(&TokenType::RightParen) {
             loop {
-                // TODO: finish call checks for expressions. This needs to change so that annonymous functions can be passed as arguments.
-                // So that a function declaration could be an argument to another function.
-                // This is a feature that is not yet implemented.
+                // anonymous functions are primary expressions, so they can be passed as arguments here
                 arguments.push(self.expression()?);
 
                 if arguments.len() >= 255 {
@@ -780,13 +1011,103 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// # interpolation
+    /// parse an interpolated string. The scanner splits `"a ${x} b"` into an Interpolation token holding "a ", the tokens of `x`, and a String token holding " b". A string with several `${}` has one Interpolation token before each of them.
+    fn interpolation(&mut self) -> Result<Expr, ParseError> {
+        let quote = self.previous().unwrap().clone();
+        let mut parts = Vec::new();
+
+        loop {
+            parts.push(Expr::Literal {
+                value: self.previous().unwrap().literal.clone(),
+            });
+            parts.push(self.expression()?);
+
+            if self.match_symbol(&[TokenType::String]) {
+                parts.push(Expr::Literal {
+                    value: self.previous().unwrap().literal.clone(),
+                });
+                break;
+            }
+
+            self.consume(
+                TokenType::Interpolation,
+                "Expect '}' after interpolated expression.",
+            )?;
+        }
+
+        Ok(Expr::Interpolation { quote, parts })
+    }
+
+    /// # list
+    ///
+    /// Parses the elements of a list literal. The opening bracket has already been consumed.
+    ///
+    /// _rule_:
+    ///
+    /// list           → "[" ( expression ( "," expression )* )? "]" ;
+    ///
+    fn list(&mut self) -> Result<Expr, ParseError> {
+        let mut elements: Vec<Expr> = vec![];
+
+        if !self.check(&TokenType::RightBracket) {
+            loop {
+                elements.push(self.expression()?);
+
+                if !self.match_symbol(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let bracket = self
+            .consume(TokenType::RightBracket, "Expect ']' after list elements.")?
+            .clone();
+
+        Ok(Expr::List { bracket, elements })
+    }
+
+    /// # map
+    ///
+    /// Parses the entries of a map literal. The opening brace has already been consumed.
+    ///
+    /// _rule_:
+    ///
+    /// map            → "{" ( expression ":" expression ( "," expression ":" expression )* )? "}" ;
+    ///
+    fn map(&mut self) -> Result<Expr, ParseError> {
+        let mut entries: Vec<(Expr, Expr)> = vec![];
+
+        if !self.check(&TokenType::RightBrace) {
+            loop {
+                let key = self.expression()?;
+                self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+                let value = self.expression()?;
+
+                entries.push((key, value));
+
+                if !self.match_symbol(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let brace = self
+            .consume(TokenType::RightBrace, "Expect '}' after map entries.")?
+            .clone();
+
+        Ok(Expr::Map { brace, entries })
+    }
+
     /// # Primary
     ///
     /// Primary is the highest level of precedence. This rule processes Objectstrings and numbers as well as booleans and all expressions:
     ///
     /// _rule_:
     ///
-    /// primary        → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" ;
+    /// primary        → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" | lambda | list | map ;
+    ///
+    /// lambda         → "fun" "(" parameters? ")" block ;
     ///
     fn primary(&mut self) -> Result<Expr, ParseError> {
         if self.match_symbol(&[TokenType::False]) {
@@ -812,14 +1133,23 @@ impl<'a> Parser<'a> {
             }
 
             return Ok(Expr::Literal { value: prev_object });
+        } else if self.match_symbol(&[TokenType::Interpolation]) {
+            return self.interpolation();
         } else if self.match_symbol(&[TokenType::Super]) {
             let keyword = self.previous().unwrap().clone();
             self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
             let method = self.consume(TokenType::Identifier, "Expect a superclass method name")?;
             return Ok(Expr::Super {
                 keyword,
-                method: method.clone(),
+                method: Box::new(method.clone()),
             });
+        } else if self.match_symbol(&[TokenType::Fun]) {
+            return self.lambda();
+        } else if self.match_symbol(&[TokenType::LeftBracket]) {
+            return self.list();
+        } else if self.match_symbol(&[TokenType::LeftBrace]) {
+            // a brace at the start of a statement is a block, so a map literal only appears here inside an expression
+            return self.map();
         } else if self.match_symbol(&[TokenType::This]) {
             return Ok(Expr::This {
                 keyword: self.previous().unwrap().clone(),
@@ -887,6 +1217,11 @@ impl<'a> Parser<'a> {
     /// Catches exceptions at statement boundaries, and brings the parser to the correct state. This prevents unwanted error messages from polluting the user's dev experience.
     ///
     fn synchronize(&mut self) {
+        // an error just before a block's closing brace must leave the brace for the block
+        if self.block_depth > 0 && self.check(&TokenType::RightBrace) {
+            return;
+        }
+
         self.advance();
 
         while !self.is_at_end() {
@@ -895,32 +1230,40 @@ impl<'a> Parser<'a> {
             }
 
             match self.peek().unwrap().token_type {
-                TokenType::Class => {}
-                TokenType::Fun => {}
-                TokenType::Var => {}
-                TokenType::For => {}
-                TokenType::If => {}
-                TokenType::While => {}
-                TokenType::Print => {}
-                TokenType::Return => (),
+                TokenType::Break
+                | TokenType::Class
+                | TokenType::Continue
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::Import
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Try => return,
+                TokenType::RightBrace if self.block_depth > 0 => return,
                 _ => {
                     self.advance();
                 }
             }
         }
-
-        self.advance();
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
     use colored::*;
 
     use super::*;
+    use crate::error::error::ErrorReporter;
     use crate::grammar::object::Object;
     use crate::grammar::token::Token;
     use crate::grammar::token::TokenType;
+    use crate::scanner::Scanner;
 
     #[test]
     fn test_finish_call() {
@@ -1081,4 +1424,288 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_for_statement_keeps_increment_out_of_body() {
+        println!(
+            "{} {}",
+            "test_for_statement:".green(),
+            "The for statement should desugar into a while loop that carries its increment, so continue cannot skip it".blue()
+        );
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new(
+            "for (var i = 0; i < 3; i = i + 1) { continue; }".to_string(),
+            error_reporter,
+        );
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        let statements = parser.parse().unwrap();
+
+        match &statements[0] {
+            Stmt::Block(block) => match &block.statements[1] {
+                Stmt::While {
+                    body, increment, ..
+                } => {
+                    assert!(increment.is_some());
+                    match body.as_ref() {
+                        Stmt::Block(body) => match body.statements[..] {
+                            [Stmt::Continue { .. }] => {}
+                            _ => panic!("Expected the body to only hold the continue statement"),
+                        },
+                        _ => panic!("Expected block statement"),
+                    }
+                }
+                _ => panic!("Expected while statement"),
+            },
+            _ => panic!("Expected block statement"),
+        }
+    }
+
+    #[test]
+    fn test_lambda_as_call_argument() {
+        println!(
+            "{} {}",
+            "test_lambda:".green(),
+            "An anonymous function should parse as a call argument in 'map(fun (a) { return a; });'".blue()
+        );
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new("map(fun (a) { return a; });".to_string(), error_reporter);
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        let statements = parser.parse().unwrap();
+
+        match &statements[0] {
+            Stmt::Expression { expression } => match expression.as_ref() {
+                Expr::Call { arguments, .. } => match &arguments[0] {
+                    Expr::Lambda(declaration) => {
+                        assert_eq!(declaration.params.len(), 1);
+                        assert_eq!(declaration.body.len(), 1);
+                    }
+                    _ => panic!("Expected lambda expression"),
+                },
+                _ => panic!("Expected call expression"),
+            },
+            _ => panic!("Expected expression statement"),
+        }
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        println!(
+            "{} {}",
+            "test_index_assignment:".green(),
+            "Assigning to an index expression 'xs[0] = [1, 2];' should parse into an IndexSet with a list value".blue()
+        );
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new("xs[0] = [1, 2];".to_string(), error_reporter);
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        let statements = parser.parse().unwrap();
+
+        match &statements[0] {
+            Stmt::Expression { expression } => match expression.as_ref() {
+                Expr::IndexSet { bracket, value, .. } => {
+                    assert_eq!(bracket.token_type, TokenType::RightBracket);
+                    match value.as_ref() {
+                        Expr::List { elements, .. } => assert_eq!(elements.len(), 2),
+                        _ => panic!("Expected list expression"),
+                    }
+                }
+                _ => panic!("Expected index set expression"),
+            },
+            _ => panic!("Expected expression statement"),
+        }
+    }
+
+    #[test]
+    fn test_class_methods_are_kept_apart() {
+        println!(
+            "{} {}",
+            "test_class_methods:".green(),
+            "Methods prefixed with 'class' should be parsed into the class statement's static methods".blue()
+        );
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new(
+            "class Math { class square(n) { return n * n; } area { return 1; } }".to_string(),
+            error_reporter,
+        );
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        let statements = parser.parse().unwrap();
+
+        match &statements[0] {
+            Stmt::Class(class_stmt) => {
+                assert_eq!(class_stmt.class_methods.len(), 1);
+                assert_eq!(class_stmt.class_methods[0].name.lexeme, "square");
+                assert_eq!(class_stmt.methods.len(), 1);
+                assert_eq!(class_stmt.methods[0].name.lexeme, "area");
+            }
+            _ => panic!("Expected class statement"),
+        }
+    }
+
+    #[test]
+    fn test_setter_declaration() {
+        println!(
+            "{} {}",
+            "test_setter:".green(),
+            "'set area(value)' should parse into a setter while 'set(value)' stays a method named set".blue()
+        );
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new(
+            "class Circle { set area(value) {} set(value) {} }".to_string(),
+            error_reporter,
+        );
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        let statements = parser.parse().unwrap();
+
+        match &statements[0] {
+            Stmt::Class(class_stmt) => {
+                assert_eq!(class_stmt.methods[0].name.lexeme, "area");
+                assert!(matches!(class_stmt.methods[0].kind, FunType::Setter));
+                assert_eq!(class_stmt.methods[1].name.lexeme, "set");
+                assert!(matches!(class_stmt.methods[1].kind, FunType::Function));
+            }
+            _ => panic!("Expected class statement"),
+        }
+    }
+
+    #[test]
+    fn test_import_statements() {
+        println!(
+            "{} {}",
+            "test_import:".green(),
+            "'as' should bind a namespace and 'for' should list the imported names".blue()
+        );
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new(
+            "import \"a.lox\" as a; import \"b.lox\" for x, y; import \"c.lox\";".to_string(),
+            error_reporter,
+        );
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        let statements = parser.parse().unwrap();
+
+        match (&statements[0], &statements[1], &statements[2]) {
+            (Stmt::Import(a), Stmt::Import(b), Stmt::Import(c)) => {
+                assert_eq!(a.alias.as_ref().unwrap().lexeme, "a");
+                assert!(a.names.is_empty());
+                assert!(b.alias.is_none());
+                let names: Vec<&str> = b.names.iter().map(|name| name.lexeme.as_str()).collect();
+                assert_eq!(names, vec!["x", "y"]);
+                assert!(c.alias.is_none() && c.names.is_empty());
+            }
+            _ => panic!("Expected import statements"),
+        }
+    }
+
+    #[test]
+    fn test_string_escapes_and_interpolation() {
+        println!(
+            "{} {}",
+            "test_interpolation:".green(),
+            "escapes should be decoded and '${}' should split the string into parts".blue()
+        );
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new(
+            r#""a\tb\u{e9}"; "x ${1 + 2} y ${z}!";"#.to_string(),
+            error_reporter.clone(),
+        );
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        let statements = parser.parse().unwrap();
+        assert!(!error_reporter.borrow().had_error());
+
+        match &statements[0] {
+            Stmt::Expression { expression } => match expression.as_ref() {
+                Expr::Literal {
+                    value: Some(Object::Str(value)),
+                } => assert_eq!(value, "a\tbé"),
+                _ => panic!("Expected string literal"),
+            },
+            _ => panic!("Expected expression statement"),
+        }
+        match &statements[1] {
+            Stmt::Expression { expression } => match expression.as_ref() {
+                Expr::Interpolation { parts, .. } => {
+                    assert_eq!(parts.len(), 5);
+                    assert!(matches!(parts[1], Expr::Binary { .. }));
+                    assert!(matches!(parts[3], Expr::Variable(_)));
+                }
+                _ => panic!("Expected interpolation"),
+            },
+            _ => panic!("Expected expression statement"),
+        }
+    }
+
+    fn parse_errors(source: &str) -> Vec<(usize, String)> {
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        let mut scanner = Scanner::new(source.to_string(), error_reporter);
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens);
+
+        match parser.parse() {
+            Ok(_) => vec![],
+            Err(errors) => errors
+                .iter()
+                .map(|error| {
+                    let (message, token) = error.get_error();
+                    (token.line, message.to_string())
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_every_parse_error_is_reported() {
+        println!(
+            "{} {}",
+            "test_parse_errors:".green(),
+            "the parser should recover at statement boundaries and report each error".blue()
+        );
+        let errors = parse_errors("var a = 1\nprint a;\nvar = 2;\nvar 3;\nprint a + ;\nprint a;");
+
+        assert_eq!(
+            errors,
+            vec![
+                (2, "Expect ';' after variable declaration.".to_string()),
+                (3, "Expected variable name.".to_string()),
+                (4, "Expected variable name.".to_string()),
+                (5, "Expected expression.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_in_bodies_are_reported() {
+        println!(
+            "{} {}",
+            "test_parse_errors:".green(),
+            "errors inside blocks, functions and methods should not hide later errors".blue()
+        );
+        let errors = parse_errors(
+            "{\n  print 1 +;\n  print 2;\n}\nfun f() {\n  var = 1;\n  return 1;\n}\nclass A {\n  m() {\n    print;\n  }\n}\nprint );",
+        );
+
+        let lines: Vec<usize> = errors.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![2, 6, 11, 14]);
+
+        // an error right before the closing brace leaves the brace to close the body
+        let errors = parse_errors("fun f() {\n print 1 +\n}\nprint 2;");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].0, 3);
+    }
+
+    #[test]
+    fn test_valid_source_has_no_parse_errors() {
+        assert!(parse_errors("var a = 1; { print a; } fun f() { return a; }").is_empty());
+    }
 }
@@ -5,10 +5,9 @@ use crate::{
     grammar::{
         expr::{Expr, ExprVisitor},
         object::Object,
-        stmt::{BlockStmt, ClassStmt, FunStmt, Stmt, StmtVisitor},
+        stmt::{BlockStmt, ClassStmt, FunStmt, FunType, ImportStmt, Stmt, StmtVisitor, TryStmt},
         token::Token,
     },
-    interpreter::Interpreter,
 };
 
 #[derive(Debug, Clone)]
@@ -16,6 +15,8 @@ enum ClassType {
     None,
     Class,
     Subclass,
+    // static methods are inside a class body, but there is no instance to bind `this` to
+    StaticMethod,
 }
 
 #[derive(Debug, Clone)]
@@ -26,11 +27,83 @@ pub enum FunctionType {
     Method,
 }
 
-// TODO: Improvement -- Add scope type to the HashMap. Save index of the scope for use in the interpreter.
-// struct Scope {
-//     defined: bool,
-//     index: usize,
-// }
+#[derive(Debug, Clone)]
+enum LoopType {
+    None,
+    Loop,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// # Local
+/// Where a resolved variable lives at runtime: how many environments up the chain, and which slot in that environment.
+pub struct Local {
+    pub depth: usize,
+    pub slot: usize,
+}
+
+#[derive(Debug, Default)]
+/// # Locals
+/// The side table the resolver fills in for the interpreter, indexed by the id of the token that names each variable. Token ids are handed out in order and only to the tokens of the source, so a vector is enough and it grows with the amount of source.
+///
+/// Variables that are not in the table are globals.
+pub struct Locals {
+    locals: Vec<Option<Local>>,
+}
+
+impl Locals {
+    pub fn new() -> Self {
+        Self { locals: Vec::new() }
+    }
+
+    pub fn insert(&mut self, id: usize, local: Local) {
+        if id >= self.locals.len() {
+            self.locals.resize(id + 1, None);
+        }
+        self.locals[id] = Some(local);
+    }
+
+    pub fn get(&self, id: usize) -> Option<Local> {
+        self.locals.get(id).copied().flatten()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ScopeVariable {
+    // false while the variable's initializer is being resolved
+    defined: bool,
+    slot: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+/// # Scope
+/// A block scope. Each declaration takes the next slot, matching the order the interpreter defines locals in at runtime.
+struct Scope {
+    variables: HashMap<String, ScopeVariable>,
+    slots: usize,
+}
+
+impl Scope {
+    fn declare(&mut self, name: &str) {
+        self.variables.insert(
+            name.to_string(),
+            ScopeVariable {
+                defined: false,
+                slot: self.slots,
+            },
+        );
+        self.slots += 1;
+    }
+
+    fn define(&mut self, name: &str) {
+        match self.variables.get_mut(name) {
+            Some(variable) => variable.defined = true,
+            None => {
+                self.declare(name);
+                self.define(name);
+            }
+        }
+    }
+}
 
 /// # Resolver
 ///
@@ -38,32 +111,36 @@ pub enum FunctionType {
 ///
 /// It helps optimize the code by resolving variable scopes at compile time.
 pub struct Resolver {
-    interpreter: Rc<RefCell<Interpreter>>,
-    scopes: Vec<HashMap<String, bool>>,
+    /// The interpreter's side table. The resolver writes into it directly so that a module can be resolved while the interpreter is running the import.
+    locals: Rc<RefCell<Locals>>,
+    scopes: Vec<Scope>,
     current_function: FunctionType,
     // current_class "value tells us if we are currently inside a class declaration while traversing the syntax tree"
     current_class: ClassType,
+    // current_loop tells us if a break or continue statement has an enclosing loop to jump out of
+    current_loop: LoopType,
 }
 
 impl Resolver {
-    pub fn new(interpreter: Rc<RefCell<Interpreter>>) -> Self {
+    pub fn new(locals: Rc<RefCell<Locals>>) -> Self {
         Self {
-            interpreter,
+            locals,
             scopes: Vec::new(),
             current_function: FunctionType::None,
             current_class: ClassType::None,
+            current_loop: LoopType::None,
         }
     }
 
     fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+        self.scopes.push(Scope::default());
     }
 
     /// # Resolve
     /// The resolver walks the syntax tree and resolves each variable similar to the interpreter with differences:
     /// - no control flow - branching like if statements and loops have no effect
     /// - no side effects - when a function is visited the function is not actually run
-    pub fn resolve(&mut self, statements: &mut Vec<Stmt>) -> Result<Object, LoxError> {
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Result<Object, LoxError> {
         for statement in statements {
             self.resolve_stmt(statement)?;
         }
@@ -71,7 +148,7 @@ impl Resolver {
         Ok(Object::Nil)
     }
 
-    fn resolve_stmt(&mut self, statement: &mut Stmt) -> Result<Object, LoxError> {
+    fn resolve_stmt(&mut self, statement: &Stmt) -> Result<Object, LoxError> {
         statement.accept(self)
     }
 
@@ -93,7 +170,7 @@ impl Resolver {
         let scope = self.scopes.last_mut();
 
         if let Some(scope) = scope {
-            scope.insert(name.lexeme.clone(), false);
+            scope.declare(&name.lexeme);
         }
     }
 
@@ -107,7 +184,7 @@ impl Resolver {
 
         let scope = self.scopes.last_mut();
         if let Some(scope) = scope {
-            scope.insert(name.lexeme.clone(), true);
+            scope.define(&name.lexeme);
         }
     }
 
@@ -117,15 +194,22 @@ impl Resolver {
     ///
     /// Each time a variable is visited (anytime a variable is accessed):
     ///
-    /// The resolve_local method saves the depth of the scope between where the variable is defined and the current scope.
+    /// The resolve_local method saves the depth of the scope between where the variable is defined and the current scope, along with the variable's slot in that scope. It is saved under the id of the token that names the variable.
     ///
     /// "We start at the innermost scope and work outwards, looking in each map for a matching name. If we find the variable, we resolve it, passing in the number of scopes between the current innermost scope and the scope where the variable was found."
-    fn resolve_local(&mut self, value: &Expr, name: &Token) -> Result<Object, LoxError> {
+    fn resolve_local(&mut self, name: &Token) -> Result<Object, LoxError> {
         let scopes = &self.scopes;
 
-        for (i, scope) in scopes.into_iter().rev().enumerate() {
-            if scope.contains_key(&name.lexeme) {
-                return self.interpreter.clone().borrow_mut().resolve(value, i);
+        for (i, scope) in scopes.iter().rev().enumerate() {
+            if let Some(variable) = scope.variables.get(&name.lexeme) {
+                self.locals.borrow_mut().insert(
+                    name.id,
+                    Local {
+                        depth: i,
+                        slot: variable.slot,
+                    },
+                );
+                return Ok(Object::Nil);
             }
         }
 
@@ -137,21 +221,26 @@ impl Resolver {
     /// When a function is declared, the resolver creates a new scope for the function and resolves the function's body.
     fn resolve_function(
         &mut self,
-        fun_stmt: &mut FunStmt,
+        fun_stmt: &FunStmt,
         fun_type: FunctionType,
     ) -> Result<Object, LoxError> {
         let enclosing_function: FunctionType = self.current_function.clone();
         self.current_function = fun_type;
 
+        // a function body is never inside the loop that surrounds its declaration
+        let enclosing_loop = self.current_loop.clone();
+        self.current_loop = LoopType::None;
+
         self.begin_scope();
         for param in &fun_stmt.params {
             self.declare(param);
             self.define(param);
         }
 
-        self.resolve(&mut fun_stmt.body)?;
+        self.resolve(&fun_stmt.body)?;
         self.end_scope();
         self.current_function = enclosing_function;
+        self.current_loop = enclosing_loop;
 
         Ok(Object::Nil)
     }
@@ -193,6 +282,65 @@ impl ExprVisitor<Result<Object, LoxError>> for Resolver {
         self.resolve_expr(expression)
     }
 
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+    ) -> Result<Object, LoxError> {
+        self.resolve_expr(object)?;
+        self.resolve_expr(index)
+    }
+
+    fn visit_index_set_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<Object, LoxError> {
+        self.resolve_expr(object)?;
+        self.resolve_expr(index)?;
+        self.resolve_expr(value)
+    }
+
+    fn visit_list_expr(&mut self, _bracket: &Token, elements: &[Expr]) -> Result<Object, LoxError> {
+        for element in elements {
+            self.resolve_expr(element)?;
+        }
+
+        Ok(Object::Nil)
+    }
+
+    fn visit_map_expr(
+        &mut self,
+        _brace: &Token,
+        entries: &[(Expr, Expr)],
+    ) -> Result<Object, LoxError> {
+        for (key, value) in entries {
+            self.resolve_expr(key)?;
+            self.resolve_expr(value)?;
+        }
+
+        Ok(Object::Nil)
+    }
+
+    fn visit_lambda_expr(&mut self, declaration: &FunStmt) -> Result<Object, LoxError> {
+        self.resolve_function(declaration, FunctionType::Function)
+    }
+
+    fn visit_interpolation_expr(
+        &mut self,
+        _quote: &Token,
+        parts: &[Expr],
+    ) -> Result<Object, LoxError> {
+        for part in parts {
+            self.resolve_expr(part)?;
+        }
+
+        Ok(Object::Nil)
+    }
+
     fn visit_literal_expr(&mut self, _value: &Option<Object>) -> Result<Object, LoxError> {
         Ok(Object::Nil)
     }
@@ -217,14 +365,16 @@ impl ExprVisitor<Result<Object, LoxError>> for Resolver {
         self.resolve_expr(value)
     }
 
-    fn visit_super_expr(
-        &mut self,
-        value: &Expr,
-        keyword: &Token,
-        _method: &Token,
-    ) -> Result<Object, LoxError> {
+    fn visit_super_expr(&mut self, keyword: &Token, _method: &Token) -> Result<Object, LoxError> {
         match self.current_class {
             ClassType::Subclass => {}
+            ClassType::StaticMethod => {
+                return Err(LoxError::RuntimeError(RuntimeError::new(
+                    "Cannot use 'super' in a static method. -- Resolver:visit_super_expr()"
+                        .to_string(),
+                    keyword,
+                )))
+            }
             _ => {
                 return Err(LoxError::RuntimeError(RuntimeError::new(
                     "Cannot use 'super' outside of a class. -- Resolver:visit_super_expr()"
@@ -233,18 +383,22 @@ impl ExprVisitor<Result<Object, LoxError>> for Resolver {
                 )))
             }
         }
-        self.resolve_local(value, keyword)?;
+        self.resolve_local(keyword)?;
         Ok(Object::Nil)
     }
 
-    fn visit_this_expr(&mut self, expr: &Expr, keyword: &Token) -> Result<Object, LoxError> {
+    fn visit_this_expr(&mut self, keyword: &Token) -> Result<Object, LoxError> {
         match self.current_class {
             ClassType::None => Err(LoxError::RuntimeError(RuntimeError::new(
                 "Cannot use 'this' outside of a class. -- Resolver:visit_this_expr()".to_string(),
                 keyword,
             ))),
+            ClassType::StaticMethod => Err(LoxError::RuntimeError(RuntimeError::new(
+                "Cannot use 'this' in a static method. -- Resolver:visit_this_expr()".to_string(),
+                keyword,
+            ))),
             _ => {
-                self.resolve_local(expr, keyword)?;
+                self.resolve_local(keyword)?;
                 Ok(Object::Nil)
             }
         }
@@ -254,22 +408,22 @@ impl ExprVisitor<Result<Object, LoxError>> for Resolver {
         self.resolve_expr(right)
     }
 
-    fn visit_variable_expr(&mut self, variable: &Expr, name: &Token) -> Result<Object, LoxError> {
+    fn visit_variable_expr(&mut self, name: &Token) -> Result<Object, LoxError> {
         if let Some(scope) = self.scopes.last() {
-            if let Some(&false) = scope.get(&name.lexeme) {
+            if let Some(ScopeVariable { defined: false, .. }) = scope.variables.get(&name.lexeme) {
                 return Err(LoxError::RuntimeError(RuntimeError::new(
                     format!("Cannot read local variable in its own initializer."),
                     &name.clone(),
                 )));
             }
         }
-        self.resolve_local(variable, &name)?;
+        self.resolve_local(name)?;
         Ok(Object::Nil)
     }
 
     fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Result<Object, LoxError> {
         self.resolve_expr(value)?;
-        self.resolve_local(value, name)
+        self.resolve_local(name)
     }
 }
 
@@ -281,8 +435,8 @@ impl StmtVisitor<Result<Object, LoxError>> for Resolver {
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
-        then_branch: &mut Stmt,
-        else_branch: &mut Option<Box<Stmt>>,
+        then_branch: &Stmt,
+        else_branch: &Option<Box<Stmt>>,
     ) -> Result<Object, LoxError> {
         self.resolve_expr(condition)?;
         self.resolve_stmt(then_branch)?;
@@ -294,7 +448,11 @@ impl StmtVisitor<Result<Object, LoxError>> for Resolver {
         }
     }
 
-    fn visit_print_stmt(&mut self, expression: &Expr) -> Result<Object, LoxError> {
+    fn visit_print_stmt(
+        &mut self,
+        _keyword: &Token,
+        expression: &Expr,
+    ) -> Result<Object, LoxError> {
         self.resolve_expr(expression)
     }
 
@@ -319,9 +477,80 @@ impl StmtVisitor<Result<Object, LoxError>> for Resolver {
         }
     }
 
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &mut Stmt) -> Result<Object, LoxError> {
+    fn visit_throw_stmt(&mut self, _keyword: &Token, value: &Expr) -> Result<Object, LoxError> {
+        self.resolve_expr(value)
+    }
+
+    fn visit_try_stmt(&mut self, try_stmt: &TryStmt) -> Result<Object, LoxError> {
+        self.visit_block_stmt(&try_stmt.body)?;
+
+        // the caught variable lives in the same scope as the catch block's statements
+        if let Some(catch) = &try_stmt.catch {
+            self.begin_scope();
+            self.declare(&catch.name);
+            self.define(&catch.name);
+            self.resolve(&catch.body.statements)?;
+            self.end_scope();
+        }
+
+        if let Some(finally) = &try_stmt.finally {
+            self.visit_block_stmt(finally)?;
+        }
+
+        Ok(Object::Nil)
+    }
+
+    /// # visit_import_stmt
+    /// The module itself is resolved by the interpreter when the import runs. Here only the names it binds are declared.
+    fn visit_import_stmt(&mut self, import_stmt: &ImportStmt) -> Result<Object, LoxError> {
+        for name in import_stmt.alias.iter().chain(import_stmt.names.iter()) {
+            self.declare(name);
+            self.define(name);
+        }
+
+        Ok(Object::Nil)
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) -> Result<Object, LoxError> {
+        let enclosing_loop = self.current_loop.clone();
+        self.current_loop = LoopType::Loop;
+
         self.resolve_expr(condition)?;
-        self.resolve_stmt(body)
+        self.resolve_stmt(body)?;
+
+        if let Some(increment) = increment {
+            self.resolve_expr(increment)?;
+        }
+
+        self.current_loop = enclosing_loop;
+
+        Ok(Object::Nil)
+    }
+
+    fn visit_break_stmt(&mut self, keyword: &Token) -> Result<Object, LoxError> {
+        match self.current_loop {
+            LoopType::Loop => Ok(Object::Nil),
+            LoopType::None => Err(LoxError::RuntimeError(RuntimeError::new(
+                "Cannot use 'break' outside of a loop. -- Resolver::visit_break_stmt()".to_string(),
+                keyword,
+            ))),
+        }
+    }
+
+    fn visit_continue_stmt(&mut self, keyword: &Token) -> Result<Object, LoxError> {
+        match self.current_loop {
+            LoopType::Loop => Ok(Object::Nil),
+            LoopType::None => Err(LoxError::RuntimeError(RuntimeError::new(
+                "Cannot use 'continue' outside of a loop. -- Resolver::visit_continue_stmt()"
+                    .to_string(),
+                keyword,
+            ))),
+        }
     }
 
     fn visit_var_stmt(&mut self, name: &Token, initializer: &Expr) -> Result<Object, LoxError> {
@@ -332,15 +561,15 @@ impl StmtVisitor<Result<Object, LoxError>> for Resolver {
         Ok(Object::Nil)
     }
 
-    fn visit_block_stmt(&mut self, statements: &mut BlockStmt) -> Result<Object, LoxError> {
+    fn visit_block_stmt(&mut self, statements: &BlockStmt) -> Result<Object, LoxError> {
         self.begin_scope();
-        self.resolve(&mut statements.statements)?;
+        self.resolve(&statements.statements)?;
         self.end_scope();
 
         Ok(Object::Nil)
     }
 
-    fn visit_function_stmt(&mut self, fun_stmt: &mut FunStmt) -> Result<Object, LoxError> {
+    fn visit_function_stmt(&mut self, fun_stmt: &FunStmt) -> Result<Object, LoxError> {
         self.declare(&fun_stmt.name);
         self.define(&fun_stmt.name);
         self.resolve_function(fun_stmt, FunctionType::Function)?;
@@ -369,14 +598,24 @@ impl StmtVisitor<Result<Object, LoxError>> for Resolver {
 
             self.begin_scope();
             if let Some(scope) = self.scopes.last_mut() {
-                scope.insert("super".to_string(), true);
+                scope.define("super");
             }
         }
 
+        // static methods are resolved before the scope that binds `this` is opened
+        let class_type = self.current_class.clone();
+        self.current_class = ClassType::StaticMethod;
+
+        for method in &class_stmt.class_methods {
+            self.resolve_function(method, FunctionType::Method)?;
+        }
+
+        self.current_class = class_type;
+
         self.begin_scope();
 
         if let Some(scope) = self.scopes.last_mut() {
-            scope.insert("this".to_string(), true);
+            scope.define("this");
         } else {
             return Err(LoxError::RuntimeError(RuntimeError::new(
                 "Cannot resolve class scope.".to_string(),
@@ -384,13 +623,25 @@ impl StmtVisitor<Result<Object, LoxError>> for Resolver {
             )));
         }
 
-        for mut method in class_stmt.methods.clone() {
+        for method in &class_stmt.methods {
+            if let FunType::Setter = method.kind {
+                if method.params.len() != 1 {
+                    return Err(LoxError::RuntimeError(RuntimeError::new(
+                        format!(
+                            "Setter '{}' must take exactly one parameter. -- Resolver::visit_class_stmt()",
+                            method.name.lexeme
+                        ),
+                        &method.name,
+                    )));
+                }
+            }
+
             let function_type = if method.name.lexeme == "init" {
                 FunctionType::Initializer
             } else {
                 FunctionType::Method
             };
-            match self.resolve_function(&mut method, function_type) {
+            match self.resolve_function(method, function_type) {
                 Ok(_) => {}
                 Err(e) => return Err(e),
             }
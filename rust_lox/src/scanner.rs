@@ -5,15 +5,18 @@ use crate::error::error::ErrorReporter;
 use crate::grammar::object::Object;
 
 // use super to access a sibling sub module
-use crate::grammar::token::{Token, TokenType};
+use crate::grammar::token::{Span, Token, TokenType};
 
 pub struct Scanner {
-    source: String,
+    /// Shared with the span of every token, so errors can show the line a token is on.
+    source: Rc<str>,
     source_length: usize,
     tokens: Vec<Token>,
     start: usize,
     current: usize,
     line: usize,
+    /// One entry per `${` that is still open, counting the braces that are open inside it. When the count drops to zero the string picks up again.
+    interpolations: Vec<usize>,
     error_reporter: Rc<RefCell<ErrorReporter>>,
     // keywords: HashMap<String, TokenType>,
 }
@@ -23,29 +26,37 @@ impl Scanner {
         let mut keywords = HashMap::new();
 
         keywords.insert("and".to_string(), TokenType::And);
+        keywords.insert("break".to_string(), TokenType::Break);
+        keywords.insert("catch".to_string(), TokenType::Catch);
         keywords.insert("class".to_string(), TokenType::Class);
+        keywords.insert("continue".to_string(), TokenType::Continue);
         keywords.insert("else".to_string(), TokenType::Else);
         keywords.insert("false".to_string(), TokenType::False);
+        keywords.insert("finally".to_string(), TokenType::Finally);
         keywords.insert("for".to_string(), TokenType::For);
         keywords.insert("fun".to_string(), TokenType::Fun);
         keywords.insert("if".to_string(), TokenType::If);
+        keywords.insert("import".to_string(), TokenType::Import);
         keywords.insert("nil".to_string(), TokenType::Nil);
         keywords.insert("or".to_string(), TokenType::Or);
         keywords.insert("print".to_string(), TokenType::Print);
         keywords.insert("return".to_string(), TokenType::Return);
         keywords.insert("super".to_string(), TokenType::Super);
         keywords.insert("this".to_string(), TokenType::This);
+        keywords.insert("throw".to_string(), TokenType::Throw);
         keywords.insert("true".to_string(), TokenType::True);
+        keywords.insert("try".to_string(), TokenType::Try);
         keywords.insert("var".to_string(), TokenType::Var);
         keywords.insert("while".to_string(), TokenType::While);
 
         Self {
-            source,
+            source: Rc::from(source),
             source_length,
             tokens: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            interpolations: Vec::new(),
             error_reporter,
             // keywords,
         }
@@ -58,13 +69,23 @@ impl Scanner {
             self.scan_token();
         }
 
-        self.tokens.push(Token::new(
+        if !self.interpolations.is_empty() {
+            self.error_reporter
+                .borrow_mut()
+                .report_error_message(self.line, "Unterminated string interpolation.");
+        }
+
+        // point just past the last token rather than at trailing blank lines
+        let end = self.source.trim_end().len();
+        let mut eof = Token::new(
             TokenType::Eof,
             String::from("EOF"),
             None,
-            self.line,
+            self.source[..end].matches('\n').count() + 1,
             generate_id(),
-        ));
+        );
+        eof.span = self.span_at(end, 0);
+        self.tokens.push(eof);
 
         &self.tokens
     }
@@ -80,9 +101,28 @@ impl Scanner {
             Some(c) => match c {
                 '(' => self.add_token(TokenType::LeftParen),
                 ')' => self.add_token(TokenType::RightParen),
-                '{' => self.add_token(TokenType::LeftBrace),
-                '}' => self.add_token(TokenType::RightBrace),
+                '{' => {
+                    if let Some(open_braces) = self.interpolations.last_mut() {
+                        *open_braces += 1;
+                    }
+                    self.add_token(TokenType::LeftBrace)
+                }
+                '}' => match self.interpolations.last_mut() {
+                    // the brace closes a `${`, so the rest of the string follows
+                    Some(1) => {
+                        self.interpolations.pop();
+                        self.string();
+                    }
+                    Some(open_braces) => {
+                        *open_braces -= 1;
+                        self.add_token(TokenType::RightBrace)
+                    }
+                    None => self.add_token(TokenType::RightBrace),
+                },
+                '[' => self.add_token(TokenType::LeftBracket),
+                ']' => self.add_token(TokenType::RightBracket),
                 ',' => self.add_token(TokenType::Comma),
+                ':' => self.add_token(TokenType::Colon),
                 '.' => self.add_token(TokenType::Dot),
                 '-' => self.add_token(TokenType::Minus),
                 '+' => self.add_token(TokenType::Plus),
@@ -163,37 +203,74 @@ impl Scanner {
     }
 
     fn add_token_with_value(&mut self, token_type: TokenType, literal: Option<Object>) {
-        let lexeme = self.source[self.start..self.current].to_string();
-
-        let token = Token::new(token_type, lexeme, literal, self.line, generate_id());
+        // start and current count characters, not bytes, so a string holding non-ASCII text cannot be sliced directly
+        let lexeme: String = self
+            .source
+            .chars()
+            .skip(self.start)
+            .take(self.current - self.start)
+            .collect();
+
+        let mut token = Token::new(token_type, lexeme, literal, self.line, generate_id());
+        let offset = self.byte_offset(self.start);
+        token.span = self.span_at(offset, self.byte_offset(self.current) - offset);
         self.tokens.push(token);
     }
 
+    /// # byte_offset
+    /// Converts a character index, which is what start and current count, into a byte offset in the source.
+    fn byte_offset(&self, index: usize) -> usize {
+        self.source
+            .char_indices()
+            .nth(index)
+            .map_or(self.source.len(), |(offset, _)| offset)
+    }
+
+    fn span_at(&self, offset: usize, length: usize) -> Span {
+        let line_start = self.source[..offset]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+
+        Span {
+            offset,
+            column: self.source[line_start..offset].chars().count() + 1,
+            length,
+            source: Some(self.source.clone()),
+        }
+    }
+
     fn identifier(&mut self) {
         while self.is_alphanumeric_or_under(self.peek()) {
             self.advance();
         }
 
-        let text = &self.source[self.start..self.current];
+        let text = &self.source[self.byte_offset(self.start)..self.byte_offset(self.current)];
         self.add_token(self.get_token_type(text));
     }
 
     fn get_token_type(&self, token: &str) -> TokenType {
         match token {
             "and" => TokenType::And,
+            "break" => TokenType::Break,
+            "catch" => TokenType::Catch,
             "class" => TokenType::Class,
+            "continue" => TokenType::Continue,
             "else" => TokenType::Else,
             "false" => TokenType::False,
+            "finally" => TokenType::Finally,
             "for" => TokenType::For,
             "fun" => TokenType::Fun,
             "if" => TokenType::If,
+            "import" => TokenType::Import,
             "nil" => TokenType::Nil,
             "or" => TokenType::Or,
             "print" => TokenType::Print,
             "return" => TokenType::Return,
             "super" => TokenType::Super,
             "this" => TokenType::This,
+            "throw" => TokenType::Throw,
             "true" => TokenType::True,
+            "try" => TokenType::Try,
             "var" => TokenType::Var,
             "while" => TokenType::While,
             _ => TokenType::Identifier,
@@ -255,30 +332,113 @@ impl Scanner {
         true
     }
 
+    /// # string
+    /// Scans a string literal, or the rest of one after an interpolated expression. The token's literal holds the decoded value.
+    ///
+    /// When a `${` is reached, the text so far becomes an Interpolation token and scanning returns to normal tokens until the matching `}`.
     fn string(&mut self) {
-        // while the next character is not a double quote and it's not EOF
-        while self.peek() != '"' && !self.is_at_end() {
-            // if there is a new line tally line index
-            if self.peek() == '\n' {
-                self.line += 1;
+        let mut value = String::new();
+
+        loop {
+            if self.is_at_end() {
+                self.error_reporter
+                    .borrow_mut()
+                    .report_error_message(self.line, "Unterminated string.");
+                return;
+            }
+
+            match self.advance() {
+                Some('"') => break,
+                Some('\\') => self.escape(&mut value),
+                Some('$') if self.peek() == '{' => {
+                    self.advance();
+                    self.add_token_with_value(TokenType::Interpolation, Some(Object::Str(value)));
+                    self.interpolations.push(1);
+                    return;
+                }
+                Some(character) => {
+                    // if there is a new line tally line index
+                    if character == '\n' {
+                        self.line += 1;
+                    }
+                    value.push(character);
+                }
+                None => {}
             }
-            // go to next char
-            self.advance();
         }
 
-        if self.is_at_end() {
+        self.add_token_with_value(TokenType::String, Some(Object::Str(value)));
+    }
+
+    /// # escape
+    /// Decodes the escape sequence after a backslash. Invalid escapes are reported on their line and left out of the string.
+    fn escape(&mut self, value: &mut String) {
+        match self.advance() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('0') => value.push('\0'),
+            Some('"') => value.push('"'),
+            Some('\\') => value.push('\\'),
+            // `\$` keeps a literal `${` from starting an interpolation
+            Some('$') => value.push('$'),
+            Some('u') => self.unicode_escape(value),
+            Some(character) => {
+                self.error_reporter.borrow_mut().report_error_message(
+                    self.line,
+                    &format!(
+                        "Invalid escape sequence '\\{}'.",
+                        character.escape_default()
+                    ),
+                );
+                if character == '\n' {
+                    self.line += 1;
+                }
+            }
+            // the string is unterminated, which is reported by the caller
+            None => {}
+        }
+    }
+
+    /// # unicode_escape
+    /// Decodes `\u{1F600}`, a code point written as one to six hex digits.
+    fn unicode_escape(&mut self, value: &mut String) {
+        if !self.match_char('{') {
             self.error_reporter
                 .borrow_mut()
-                .report_error_message(self.line, "Unterminated string.");
+                .report_error_message(self.line, "Expect '{' after '\\u'.");
             return;
         }
+        self.increment_current();
 
-        // Get the The closing " quotation mark
-        self.advance();
+        let mut digits = String::new();
+        while self.peek().is_ascii_hexdigit() {
+            digits.push(self.peek());
+            self.increment_current();
+        }
 
-        // Trim the surrounding quotes.
-        let value = &self.source[self.start + 1..self.current - 1];
-        self.add_token_with_value(TokenType::String, Some(Object::Str(value.to_string())));
+        if !self.match_char('}') {
+            self.error_reporter
+                .borrow_mut()
+                .report_error_message(self.line, "Expect '}' after unicode escape digits.");
+            return;
+        }
+        self.increment_current();
+
+        let character = match digits.len() {
+            1..=6 => u32::from_str_radix(&digits, 16)
+                .ok()
+                .and_then(char::from_u32),
+            _ => None,
+        };
+
+        match character {
+            Some(character) => value.push(character),
+            None => self.error_reporter.borrow_mut().report_error_message(
+                self.line,
+                &format!("Invalid unicode escape sequence '\\u{{{}}}'.", digits),
+            ),
+        }
     }
 
     fn number(&mut self) {
@@ -298,7 +458,76 @@ impl Scanner {
             }
         }
 
-        let number: f64 = self.source[self.start..self.current].parse().unwrap();
+        let number: f64 = self.source[self.byte_offset(self.start)..self.byte_offset(self.current)]
+            .parse()
+            .unwrap();
         self.add_token_with_value(TokenType::Number, Some(Object::Num(number)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Vec<Token> {
+        let error_reporter = Rc::new(RefCell::new(ErrorReporter::new()));
+        Scanner::new(source.to_string(), error_reporter)
+            .scan_tokens()
+            .clone()
+    }
+
+    #[test]
+    fn test_tokens_record_their_span() {
+        let tokens = scan("var total = 1;\nprint total + 20;");
+
+        let spans: Vec<(&str, usize, usize, usize)> = tokens
+            .iter()
+            .map(|token| {
+                (
+                    token.lexeme.as_str(),
+                    token.span.offset,
+                    token.span.column,
+                    token.span.length,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                ("var", 0, 1, 3),
+                ("total", 4, 5, 5),
+                ("=", 10, 11, 1),
+                ("1", 12, 13, 1),
+                (";", 13, 14, 1),
+                ("print", 15, 1, 5),
+                ("total", 21, 7, 5),
+                ("+", 27, 13, 1),
+                ("20", 29, 15, 2),
+                (";", 31, 17, 1),
+                ("EOF", 32, 18, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans_count_bytes_and_columns_count_characters() {
+        let tokens = scan("print \"héllo\" + 1;");
+
+        assert_eq!(tokens[1].span.offset, 6);
+        assert_eq!(tokens[1].span.length, 8);
+        assert_eq!(tokens[2].span.offset, 15);
+        assert_eq!(tokens[2].span.column, 15);
+        assert_eq!(tokens[3].lexeme, "1");
+    }
+
+    #[test]
+    fn test_eof_points_past_the_last_token() {
+        let tokens = scan("print 1\n\n\n");
+        let eof = tokens.last().unwrap();
+
+        assert_eq!(eof.line, 1);
+        assert_eq!(eof.span.offset, 7);
+        assert_eq!(eof.span.column, 8);
+    }
+}